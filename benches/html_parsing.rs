@@ -37,13 +37,13 @@ fn bench_html_parsers(c: &mut Criterion) {
     group.bench_with_input(
         BenchmarkId::new("Google.parse", "exit-vim"),
         include_str!("../test/google/exit-vim.html"),
-        |b, html| b.iter(|| Google.parse(html, &sites, limit)),
+        |b, html| b.iter(|| Google::default().parse(html, &sites, limit)),
     );
 
     group.bench_with_input(
         BenchmarkId::new("DuckDuckGo.parse", "exit-vim"),
         include_str!("../test/duckduckgo/exit-vim.html"),
-        |b, html| b.iter(|| DuckDuckGo.parse(html, &sites, limit)),
+        |b, html| b.iter(|| DuckDuckGo::default().parse(html, &sites, limit)),
     );
 
     let mut sites = HashMap::new();
@@ -55,7 +55,7 @@ fn bench_html_parsers(c: &mut Criterion) {
     group.bench_with_input(
         BenchmarkId::new("Google.parse", "q"),
         include_str!("../test/google/parsing-q.html"),
-        |b, html| b.iter(|| Google.parse(html, &sites, limit)),
+        |b, html| b.iter(|| Google::default().parse(html, &sites, limit)),
     );
 
     let mut sites = HashMap::new();
@@ -64,7 +64,7 @@ fn bench_html_parsers(c: &mut Criterion) {
     group.bench_with_input(
         BenchmarkId::new("DuckDuckGo.parse", "tagged"),
         include_str!("../test/duckduckgo/tagged.html"),
-        |b, html| b.iter(|| DuckDuckGo.parse(html, &sites, limit)),
+        |b, html| b.iter(|| DuckDuckGo::default().parse(html, &sites, limit)),
     );
 
     group.finish();
@@ -0,0 +1,73 @@
+# so shell integration for bash: records the exit status, command line, and
+# stderr of failed commands, so that `so --last-error` can search their error.
+# Add to ~/.bashrc:
+#
+#     eval "$(so --shell-init bash)"
+#
+# Note that stderr is teed into a file, so programs no longer see a terminal
+# there, and some may stop coloring their errors.
+__so_dir=__SO_DIR__
+# Each shell captures its stderr separately
+__so_stderr=$__so_dir/stderr-$$
+mkdir -p "$__so_dir"
+: >"$__so_stderr"
+exec 2> >(tee -a "$__so_stderr" >&2)
+
+__so_preexec() {
+    __so_command=$1
+    : >"$__so_stderr"
+}
+
+__so_precmd() {
+    local code=$?
+    if ((code != 0)) && [[ -n ${__so_command:-} ]]; then
+        {
+            printf '%s\n%s\n' "$code" "${__so_command//$'\n'/ }"
+            tail -c __SO_TAIL__ "$__so_stderr"
+        } >"$__so_dir/last-error"
+    fi
+    __so_command=
+    return $code
+}
+
+# Chain a command onto any existing trap on a signal, running that first since
+# it may rely on `$_`, as bash-preexec's DEBUG trap does
+__so_trap() {
+    local trap
+    trap=$(trap -p "$2")
+    trap=${trap#trap -- }
+    trap=${trap% "$2"}
+    eval "trap=${trap:-''}"
+    trap "${trap:+$trap
+}$1" "$2"
+}
+
+__so_trap 'rm -f "$__so_stderr"' EXIT
+
+if [[ -n ${bash_preexec_imported:-} ]]; then
+    preexec_functions+=(__so_preexec)
+    precmd_functions=(__so_precmd "${precmd_functions[@]}")
+else
+    __so_debug() {
+        # Only the first command after the prompt, not each within PROMPT_COMMAND
+        [[ -n ${__so_at_prompt:-} ]] || return 0
+        __so_at_prompt=
+        if [[ ! -o history ]]; then
+            __so_preexec "$BASH_COMMAND"
+            return 0
+        fi
+        # The whole command line, unless it was kept out of the history, e.g.
+        # with HISTCONTROL=ignorespace, in which case it's left unrecorded. A
+        # repeated line may be kept out too, but then matches the last entry.
+        local entry
+        entry=$(HISTTIMEFORMAT= builtin history 1)
+        if [[ $entry =~ ^\ *[0-9]+\*?\ +(.*)$ ]] &&
+            [[ $HISTCMD != "${__so_histcmd:-}" || ${BASH_REMATCH[1]} == "$BASH_COMMAND"* ]]; then
+            __so_preexec "${BASH_REMATCH[1]}"
+        fi
+        __so_histcmd=$HISTCMD
+    }
+
+    __so_trap __so_debug DEBUG
+    PROMPT_COMMAND="__so_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}; __so_at_prompt=1"
+fi
@@ -0,0 +1,20 @@
+# so shell integration for fish: records failed commands, so that
+# `so --last-error` can point at them. Add to ~/.config/fish/config.fish:
+#
+#     so --shell-init fish | source
+#
+# fish can't redirect its own stderr, so only the exit status and command line
+# are recorded, and `so --last-error` suggests rerunning the command with
+# `so --run`.
+set -g __so_dir __SO_DIR__
+
+function __so_postexec --on-event fish_postexec
+    set -l code $status
+    if test $code -ne 0; and test -n "$argv[1]"
+        mkdir -p $__so_dir
+        begin
+            echo $code
+            string join ' ' -- (string split \n -- $argv[1])
+        end >$__so_dir/last-error
+    end
+end
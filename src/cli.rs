@@ -1,10 +1,16 @@
 use clap::{
-    builder::{styling::AnsiColor as Ansi, Styles},
+    builder::{styling::AnsiColor as Ansi, Resettable, Styles},
     value_parser, Arg, ArgAction, ArgMatches, ColorChoice, Command,
 };
 
-use crate::config::Config;
-use crate::error::Result;
+use std::env;
+use std::io::{self, IsTerminal, Read};
+
+use crate::config::{Config, Date, SearchEngine, SearchFilters, SearchSort};
+use crate::error::{Error, Result};
+use crate::project;
+use crate::query;
+use crate::shell::Shell;
 
 // TODO --add-site (in addition to defaults)
 // TODO set_api_key should probably just be a bool, since we have config
@@ -13,13 +19,57 @@ pub struct Opts {
     pub print_config_path: bool,
     pub update_sites: bool,
     pub set_api_key: Option<String>,
+    pub open: Option<String>,
     pub query: Option<String>,
+    /// Whether the query was read from stdin, and so is likely command output
+    pub piped_query: bool,
+    /// Command to run, searching its error output if it fails
+    pub run: Option<Vec<String>>,
+    /// Shell to print the integration snippet for
+    pub shell_init: Option<Shell>,
+    /// Search the last failed command's error, as recorded by the shell integration
+    pub last_error: bool,
+    pub show_query: bool,
     pub config: Config,
 }
 
-/// Get CLI opts and args, with defaults pulled from user configuration
+/// Get CLI opts and args, with defaults pulled from user configuration. When
+/// stdin isn't a terminal, e.g. `cmd 2>&1 | so`, the query can be piped in.
 pub fn get_opts() -> Result<Opts> {
-    get_opts_with(Config::new, |a| a.get_matches())
+    let piped = !io::stdin().is_terminal();
+    let mut opts = get_opts_with(Config::new, |a| {
+        if piped {
+            a.mut_arg("query", |arg| {
+                arg.required_unless_present(Resettable::Reset)
+            })
+            .get_matches()
+        } else {
+            a.get_matches()
+        }
+    })?;
+    if piped && opts.wants_query() && opts.query.is_none() {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        let query = query::error_line(&input)
+            .ok_or_else(|| Error::NoQuery(String::from("stdin is empty")))?;
+        opts.query = Some(query);
+        opts.piped_query = true;
+    }
+    Ok(opts)
+}
+
+impl Opts {
+    /// Whether the options call for a search query, rather than e.g. listing sites
+    fn wants_query(&self) -> bool {
+        !(self.list_sites
+            || self.print_config_path
+            || self.update_sites
+            || self.set_api_key.is_some()
+            || self.open.is_some()
+            || self.run.is_some()
+            || self.shell_init.is_some()
+            || self.last_error)
+    }
 }
 
 /// Get CLI opts, starting with defaults produced from `mk_config` and matching args with
@@ -32,13 +82,29 @@ where
     let config = mk_config()?;
     let limit = config.limit.to_string();
     let sites = config.sites.join(";");
-    let engine = config.search_engine.to_string();
+    let engines = config
+        .search_engine
+        .iter()
+        .map(SearchEngine::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let engine_names = SearchEngine::BUILTIN
+        .iter()
+        .map(|name| name.to_string())
+        .chain(config.engines.iter().map(|engine| engine.name.clone()))
+        .collect::<Vec<_>>();
     let clapp = Command::new("so")
         .color(ColorChoice::Always)
         .styles(STYLES)
         .version(clap::crate_version!())
         .author(clap::crate_authors!())
         .about(clap::crate_description!())
+        .after_help(
+            "Search engines other than StackExchange can't filter results, so \
+            --accepted, --min-score, --from, --to, --exclude-closed, and --title-only \
+            are applied to their results after fetching, which may leave fewer than \
+            --limit of them.",
+        )
         .arg(
             Arg::new("list-sites")
                 .long("list-sites")
@@ -85,6 +151,104 @@ where
                 .value_parser(value_parser!(u16))
                 .help("Question limit"),
         )
+        .arg(
+            Arg::new("tag")
+                .long("tag")
+                .short('t')
+                .action(ArgAction::Append)
+                .num_args(1)
+                .value_name("tag")
+                .help("Restrict the search to questions with this tag"),
+        )
+        .arg(
+            Arg::new("auto-tag")
+                .long("auto-tag")
+                .action(ArgAction::SetTrue)
+                .help("Tag the search with the language of the project in the working directory, on StackOverflow only"),
+        )
+        .arg(
+            Arg::new("no-auto-tag")
+                .long("no-auto-tag")
+                .action(ArgAction::SetTrue)
+                .help("Disable auto-tag")
+                .conflicts_with("auto-tag")
+                .hide(!config.auto_tag),
+        )
+        .arg(
+            Arg::new("accepted")
+                .long("accepted")
+                .action(ArgAction::SetTrue)
+                .help("Only show questions with an accepted answer"),
+        )
+        .arg(
+            Arg::new("no-accepted")
+                .long("no-accepted")
+                .action(ArgAction::SetTrue)
+                .help("Disable accepted")
+                .conflicts_with("accepted")
+                .hide(!config.filters.accepted),
+        )
+        .arg(
+            Arg::new("min-score")
+                .long("min-score")
+                .num_args(1)
+                .value_name("int")
+                .value_parser(value_parser!(i32))
+                .allow_negative_numbers(true)
+                .help("Only show questions with at least this score"),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .num_args(1)
+                .value_name("YYYY-MM-DD")
+                .value_parser(|s: &str| s.parse::<Date>())
+                .help("Only show questions asked on or after this date"),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .num_args(1)
+                .value_name("YYYY-MM-DD")
+                .value_parser(|s: &str| s.parse::<Date>())
+                .help("Only show questions asked on or before this date"),
+        )
+        .arg(
+            Arg::new("exclude-closed")
+                .long("exclude-closed")
+                .action(ArgAction::SetTrue)
+                .help("Leave out closed questions"),
+        )
+        .arg(
+            Arg::new("no-exclude-closed")
+                .long("no-exclude-closed")
+                .action(ArgAction::SetTrue)
+                .help("Disable exclude-closed")
+                .conflicts_with("exclude-closed")
+                .hide(!config.filters.exclude_closed),
+        )
+        .arg(
+            Arg::new("title-only")
+                .long("title-only")
+                .action(ArgAction::SetTrue)
+                .help("Only match the query against question titles"),
+        )
+        .arg(
+            Arg::new("no-title-only")
+                .long("no-title-only")
+                .action(ArgAction::SetTrue)
+                .help("Disable title-only")
+                .conflicts_with("title-only")
+                .hide(!config.filters.title_only),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .num_args(1)
+                .value_name("order")
+                .value_parser(SearchSort::ALL.to_vec())
+                .help("Sort results by relevance, votes, activity, or creation"),
+        )
         .arg(
             Arg::new("lucky")
                 .long("lucky")
@@ -99,6 +263,34 @@ where
                 .conflicts_with("lucky")
                 .hide(!config.lucky),
         )
+        .arg(
+            Arg::new("no-normalize")
+                .long("no-normalize")
+                .action(ArgAction::SetTrue)
+                .help("Search the query as is, rather than stripping paths, line numbers, etc. from error messages"),
+        )
+        .arg(
+            Arg::new("normalize")
+                .long("normalize")
+                .action(ArgAction::SetTrue)
+                .help("Enable query normalization")
+                .conflicts_with("no-normalize")
+                .hide(config.normalize_query),
+        )
+        .arg(
+            Arg::new("show-query")
+                .long("show-query")
+                .action(ArgAction::SetTrue)
+                .help("Show the query as searched"),
+        )
+        .arg(
+            Arg::new("open")
+                .long("open")
+                .num_args(1)
+                .value_name("url")
+                .conflicts_with("query")
+                .help("Open a question or answer by its URL, or by site-code:question-id"),
+        )
         .arg(
             Arg::new("query")
                 .num_args(1..)
@@ -108,17 +300,56 @@ where
                     "update-sites",
                     "set-api-key",
                     "print-config-path",
+                    "open",
+                    "run",
+                    "shell-init",
+                    "last-error",
                 ]),
         )
+        .arg(
+            Arg::new("run")
+                .long("run")
+                .action(ArgAction::SetTrue)
+                .requires("command")
+                .conflicts_with_all(["query", "open"])
+                .help("Run a command, searching its error output if it fails, e.g. `so --run -- cargo build`"),
+        )
+        .arg(
+            Arg::new("command")
+                .num_args(1..)
+                .index(2)
+                .last(true)
+                .value_name("cmd")
+                .requires("run")
+                .hide(true),
+        )
+        .arg(
+            Arg::new("shell-init")
+                .long("shell-init")
+                .num_args(1)
+                .value_name("shell")
+                .value_parser(Shell::ALL.to_vec())
+                .conflicts_with_all(["query", "open", "run"])
+                .help("Print a snippet for your shell's startup file, recording failed commands for --last-error, e.g. `eval \"$(so --shell-init bash)\"`"),
+        )
+        .arg(
+            Arg::new("last-error")
+                .long("last-error")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["query", "open", "run", "shell-init"])
+                .help("Search the error of the last failed command, as recorded by --shell-init"),
+        )
         .arg(
             Arg::new("search-engine")
                 .long("search-engine")
                 .short('e')
+                .action(ArgAction::Append)
                 .num_args(1)
-                .default_value(&engine)
+                .value_delimiter(',')
+                .default_value(&engines)
                 .value_name("engine")
-                .value_parser(["duckduckgo", "google", "stackexchange"])
-                .help("Use specified search engine")
+                .value_parser(engine_names)
+                .help("Use specified search engine, falling back to any others in order")
                 .next_line_help(true),
         );
     let matches = get_matches(clapp);
@@ -127,20 +358,102 @@ where
         (_, true) => false,
         _ => config.lucky,
     };
+    let normalize_query = match (
+        matches.get_flag("normalize"),
+        matches.get_flag("no-normalize"),
+    ) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => config.normalize_query,
+    };
+    let auto_tag = match (
+        matches.get_flag("auto-tag"),
+        matches.get_flag("no-auto-tag"),
+    ) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => config.auto_tag,
+    };
+    let accepted = match (
+        matches.get_flag("accepted"),
+        matches.get_flag("no-accepted"),
+    ) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => config.filters.accepted,
+    };
+    let exclude_closed = match (
+        matches.get_flag("exclude-closed"),
+        matches.get_flag("no-exclude-closed"),
+    ) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => config.filters.exclude_closed,
+    };
+    let title_only = match (
+        matches.get_flag("title-only"),
+        matches.get_flag("no-title-only"),
+    ) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => config.filters.title_only,
+    };
+    let filters = SearchFilters {
+        accepted,
+        min_score: matches
+            .get_one("min-score")
+            .copied()
+            .or(config.filters.min_score),
+        from_date: matches
+            .get_one("from")
+            .copied()
+            .or(config.filters.from_date),
+        to_date: matches.get_one("to").copied().or(config.filters.to_date),
+        exclude_closed,
+        title_only,
+        // safe via clap's possible values
+        sort: matches
+            .get_one::<String>("sort")
+            .map_or(config.filters.sort, |sort| sort.parse().unwrap()),
+    };
+    let tags: Vec<String> = matches
+        .get_many::<String>("tag")
+        .map(|tags| tags.cloned().collect())
+        .unwrap_or_else(|| config.tags.clone());
+    let project_tag = if auto_tag && tags.is_empty() {
+        env::current_dir()
+            .ok()
+            .and_then(|dir| project::project_tag(&dir))
+    } else {
+        None
+    };
     Ok(Opts {
         list_sites: matches.get_flag("list-sites"),
         print_config_path: matches.get_flag("print-config-path"),
         update_sites: matches.get_flag("update-sites"),
         set_api_key: matches.get_one("set-api-key").cloned(),
+        open: matches.get_one("open").cloned(),
         query: matches
             .get_many::<String>("query")
             .map(|words| words.map(|s| s.as_str()).collect::<Vec<_>>().join(" ")),
+        piped_query: false,
+        run: matches
+            .get_many::<String>("command")
+            .map(|args| args.cloned().collect()),
+        // safe via clap's possible values
+        shell_init: matches
+            .get_one::<String>("shell-init")
+            .map(|shell| shell.parse().unwrap()),
+        last_error: matches.get_flag("last-error"),
+        show_query: matches.get_flag("show-query"),
         config: Config {
             // these unwraps are safe via clap default values & validators
             limit: *matches.get_one("limit").unwrap(),
-            search_engine: serde_yaml::from_str(
-                matches.get_one::<String>("search-engine").unwrap(),
-            )?,
+            search_engine: matches
+                .get_many::<String>("search-engine")
+                .unwrap()
+                .map(|engine| SearchEngine::from(engine.clone()))
+                .collect(),
             sites: matches
                 .get_many::<String>("site")
                 .expect("at least one site is required!")
@@ -149,6 +462,11 @@ where
                 .collect(),
             api_key: matches.get_one("set-api-key").cloned().or(config.api_key),
             lucky,
+            tags,
+            auto_tag,
+            project_tag,
+            filters,
+            normalize_query,
             ..config
         },
     })
@@ -163,7 +481,7 @@ const STYLES: Styles = Styles::styled()
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::SearchEngine;
+    use crate::config::CustomEngine;
 
     fn defaults() -> Config {
         Config {
@@ -175,8 +493,28 @@ mod tests {
                 String::from("sites"),
                 String::from("yeah"),
             ],
-            search_engine: SearchEngine::DuckDuckGo,
+            tags: vec![],
+            auto_tag: false,
+            project_tag: None,
+            filters: SearchFilters::default(),
+            normalize_query: true,
+            search_engine: vec![SearchEngine::DuckDuckGo],
             copy_cmd: Some(String::from("wl-copy")),
+            connect_timeout: 5,
+            read_timeout: 15,
+            retries: 1,
+            proxy: None,
+            no_proxy: vec![],
+            ca_certs: vec![],
+            api_url: None,
+            api_version: None,
+            duckduckgo_url: None,
+            google_url: None,
+            bing_url: None,
+            brave_url: None,
+            startpage_url: None,
+            searxng_url: None,
+            engines: vec![],
         }
     }
 
@@ -219,6 +557,224 @@ mod tests {
                 ..defaults()
             }
         );
+
+        let opts = get_opts_with(mk_config, |a| {
+            a.get_matches_from(vec![
+                "so",
+                "-e",
+                "google,duckduckgo",
+                "-e",
+                "stackexchange",
+                "how do I exit Vim",
+            ])
+        });
+
+        assert_eq!(
+            opts.unwrap().config,
+            Config {
+                search_engine: vec![
+                    SearchEngine::Google,
+                    SearchEngine::DuckDuckGo,
+                    SearchEngine::StackExchange
+                ],
+                ..defaults()
+            }
+        );
+    }
+
+    #[test]
+    fn test_custom_engine() {
+        let with_engine = || {
+            Ok(Config {
+                engines: vec![CustomEngine {
+                    name: String::from("qwant"),
+                    url: String::from("https://lite.qwant.com/?q={query}"),
+                    selector: String::from("a.url"),
+                    href_attribute: None,
+                    redirect_param: None,
+                    blocked: None,
+                }],
+                ..defaults()
+            })
+        };
+        let opts = get_opts_with(with_engine, |a| {
+            a.get_matches_from(vec!["so", "-e", "qwant,google", "how do I exit Vim"])
+        });
+        assert_eq!(
+            opts.unwrap().config.search_engine,
+            vec![
+                SearchEngine::Custom(String::from("qwant")),
+                SearchEngine::Google
+            ]
+        );
+
+        // Undefined engines are rejected
+        let opts = get_opts_with(mk_config, |a| {
+            let undefined = vec!["so", "-e", "qwant", "how do I exit Vim"];
+            assert!(a.clone().try_get_matches_from(undefined).is_err());
+            a.get_matches_from(vec!["so", "how do I exit Vim"])
+        });
+        assert!(opts.is_ok());
+    }
+
+    #[test]
+    fn test_tags() {
+        let opts = get_opts_with(mk_config, |a| {
+            a.get_matches_from(vec![
+                "so",
+                "-t",
+                "vim",
+                "--tag",
+                "exit",
+                "how do I exit Vim",
+            ])
+        });
+        assert_eq!(
+            opts.unwrap().config,
+            Config {
+                tags: vec![String::from("vim"), String::from("exit")],
+                ..defaults()
+            }
+        );
+
+        // Tests run in the crate's directory
+        let opts = get_opts_with(mk_config, |a| {
+            a.get_matches_from(vec!["so", "--auto-tag", "how do I exit Vim"])
+        });
+        assert_eq!(
+            opts.unwrap().config,
+            Config {
+                auto_tag: true,
+                project_tag: Some(String::from("rust")),
+                ..defaults()
+            }
+        );
+
+        // Explicit tags take precedence
+        let with_auto_tag = || {
+            Ok(Config {
+                auto_tag: true,
+                ..defaults()
+            })
+        };
+        let opts = get_opts_with(with_auto_tag, |a| {
+            a.get_matches_from(vec!["so", "-t", "vim", "how do I exit Vim"])
+        });
+        let config = opts.unwrap().config;
+        assert_eq!(config.tags, vec![String::from("vim")]);
+        assert_eq!(config.project_tag, None);
+
+        let opts = get_opts_with(with_auto_tag, |a| {
+            a.get_matches_from(vec!["so", "--no-auto-tag", "how do I exit Vim"])
+        });
+        assert_eq!(opts.unwrap().config, defaults());
+    }
+
+    #[test]
+    fn test_filters() {
+        let opts = get_opts_with(mk_config, |a| {
+            a.get_matches_from(vec![
+                "so",
+                "--accepted",
+                "--min-score",
+                "-2",
+                "--from",
+                "2020-01-31",
+                "--sort",
+                "votes",
+                "how do I exit Vim",
+            ])
+        });
+        assert_eq!(
+            opts.unwrap().config.filters,
+            SearchFilters {
+                accepted: true,
+                min_score: Some(-2),
+                from_date: Some("2020-01-31".parse().unwrap()),
+                sort: SearchSort::Votes,
+                ..SearchFilters::default()
+            }
+        );
+
+        // Flags add to the configured filters
+        let with_filters = || {
+            Ok(Config {
+                filters: SearchFilters {
+                    exclude_closed: true,
+                    min_score: Some(10),
+                    ..SearchFilters::default()
+                },
+                ..defaults()
+            })
+        };
+        let opts = get_opts_with(with_filters, |a| {
+            a.get_matches_from(vec!["so", "--title-only", "--min-score", "1", "exit Vim"])
+        });
+        assert_eq!(
+            opts.unwrap().config.filters,
+            SearchFilters {
+                exclude_closed: true,
+                title_only: true,
+                min_score: Some(1),
+                ..SearchFilters::default()
+            }
+        );
+
+        // Configured flags can be turned off
+        let with_flags = || {
+            Ok(Config {
+                filters: SearchFilters {
+                    accepted: true,
+                    exclude_closed: true,
+                    title_only: true,
+                    ..SearchFilters::default()
+                },
+                ..defaults()
+            })
+        };
+        let opts = get_opts_with(with_flags, |a| {
+            a.get_matches_from(vec![
+                "so",
+                "--no-accepted",
+                "--no-exclude-closed",
+                "--no-title-only",
+                "exit Vim",
+            ])
+        });
+        assert_eq!(opts.unwrap().config.filters, SearchFilters::default());
+
+        // Invalid dates and sorts are rejected
+        let opts = get_opts_with(mk_config, |a| {
+            for invalid in [["--to", "2021-02-29"], ["--sort", "random"]] {
+                let args = ["so"].iter().chain(&invalid).chain(&["exit Vim"]);
+                assert!(a.clone().try_get_matches_from(args).is_err());
+            }
+            a.get_matches_from(vec!["so", "how do I exit Vim"])
+        });
+        assert!(opts.is_ok());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let opts = get_opts_with(mk_config, |a| {
+            a.get_matches_from(vec!["so", "--no-normalize", "--show-query", "exit Vim"])
+        })
+        .unwrap();
+        assert!(!opts.config.normalize_query);
+        assert!(opts.show_query);
+
+        let without_normalize = || {
+            Ok(Config {
+                normalize_query: false,
+                ..defaults()
+            })
+        };
+        let opts = get_opts_with(without_normalize, |a| {
+            a.get_matches_from(vec!["so", "--normalize", "exit Vim"])
+        })
+        .unwrap();
+        assert_eq!(opts.config, defaults());
+        assert!(!opts.show_query);
     }
 
     #[test]
@@ -241,6 +797,70 @@ mod tests {
         assert_eq!(opts.set_api_key, Some(String::from("new key")));
     }
 
+    #[test]
+    fn test_open() {
+        let opts = get_opts_with(mk_config, |a| {
+            a.get_matches_from(vec!["so", "--open", "stackoverflow:11828270"])
+        })
+        .unwrap();
+
+        assert_eq!(opts.open, Some(String::from("stackoverflow:11828270")));
+        assert_eq!(opts.query, None);
+    }
+
+    #[test]
+    fn test_run() {
+        let opts = get_opts_with(mk_config, |a| {
+            a.get_matches_from(vec!["so", "--run", "--", "cargo", "build", "--release"])
+        })
+        .unwrap();
+        assert_eq!(
+            opts.run,
+            Some(vec![
+                String::from("cargo"),
+                String::from("build"),
+                String::from("--release")
+            ])
+        );
+        assert_eq!(opts.query, None);
+        assert!(!opts.wants_query());
+
+        // The command needs --run, and vice versa
+        let opts = get_opts_with(mk_config, |a| {
+            assert!(a.clone().try_get_matches_from(["so", "--run"]).is_err());
+            assert!(a.clone().try_get_matches_from(["so", "--", "ls"]).is_err());
+            a.get_matches_from(vec!["so", "how do I exit Vim"])
+        })
+        .unwrap();
+        assert!(opts.wants_query());
+    }
+
+    #[test]
+    fn test_shell_init() {
+        let opts = get_opts_with(mk_config, |a| {
+            assert!(a
+                .clone()
+                .try_get_matches_from(["so", "--shell-init", "powershell"])
+                .is_err());
+            a.get_matches_from(vec!["so", "--shell-init", "zsh"])
+        })
+        .unwrap();
+        assert_eq!(opts.shell_init, Some(Shell::Zsh));
+        assert!(!opts.wants_query());
+
+        let opts = get_opts_with(mk_config, |a| {
+            assert!(a
+                .clone()
+                .try_get_matches_from(["so", "--last-error", "how do I exit Vim"])
+                .is_err());
+            a.get_matches_from(vec!["so", "--last-error"])
+        })
+        .unwrap();
+        assert!(opts.last_error);
+        assert_eq!(opts.query, None);
+        assert!(!opts.wants_query());
+    }
+
     #[test]
     #[should_panic]
     fn test_conflicts() {
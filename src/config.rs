@@ -1,25 +1,98 @@
 use directories::ProjectDirs;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::convert::TryFrom;
+use std::env;
 use std::fmt;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::Command;
 use std::process::Stdio;
+use std::str::FromStr;
 
 use crate::error::{Error, Result};
 use crate::utils;
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
+#[serde(from = "String", into = "String")]
 #[derive(Default)]
 pub enum SearchEngine {
     DuckDuckGo,
     #[default]
     Google,
     StackExchange,
+    /// StackExchange's `/similar` endpoint, matching question titles
+    StackExchangeSimilar,
+    /// StackExchange's `/search/excerpts` endpoint, a full text search
+    StackExchangeExcerpts,
+    Bing,
+    Brave,
+    Startpage,
+    /// A SearXNG instance, via its JSON API
+    Searxng,
+    /// Google, DuckDuckGo, and StackExchange combined
+    Meta,
+    /// An engine defined under `engines` in the config file
+    Custom(String),
 }
 
+/// A search engine defined in the config file, scraped like the built in ones
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CustomEngine {
+    /// Name to select the engine by; built in engine names take precedence
+    pub name: String,
+    /// Search URL with `{query}`, `{sites}`, and optionally `{page}` placeholders
+    pub url: String,
+    /// CSS selector for the result links
+    pub selector: String,
+    /// Attribute of the result links holding their URL, `href` by default
+    pub href_attribute: Option<String>,
+    /// Query parameter holding the result URL, for engines linking via redirects
+    pub redirect_param: Option<String>,
+    /// CSS selector that only matches when the search is blocked, e.g. a captcha
+    pub blocked: Option<String>,
+}
+
+/// Filters narrowing down search results. The StackExchange engines pass them
+/// on to the API where possible; they are applied to the questions otherwise.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct SearchFilters {
+    /// Only questions with an accepted answer
+    pub accepted: bool,
+    /// Minimum question score
+    pub min_score: Option<i32>,
+    /// Only questions asked on or after this date
+    pub from_date: Option<Date>,
+    /// Only questions asked on or before this date
+    pub to_date: Option<Date>,
+    /// Leave out closed questions
+    pub exclude_closed: bool,
+    /// Only match the query against question titles
+    pub title_only: bool,
+    pub sort: SearchSort,
+}
+
+/// Order of search results
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// As ranked by the search engine
+    #[default]
+    Relevance,
+    /// Highest score first
+    Votes,
+    /// Most recently edited first
+    Activity,
+    /// Newest first
+    Creation,
+}
+
+/// A calendar date, written as `YYYY-MM-DD`
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Date(i64);
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(default)]
 pub struct Config {
@@ -27,21 +100,211 @@ pub struct Config {
     pub limit: u16,
     pub lucky: bool,
     pub sites: Vec<String>,
-    pub search_engine: SearchEngine,
+    /// Restrict searches to questions with all of these tags
+    pub tags: Vec<String>,
+    /// Infer a tag from the project in the working directory, when no tags
+    /// are given, e.g. `rust` in a directory with a `Cargo.toml`
+    pub auto_tag: bool,
+    /// Tag inferred with `auto_tag`. It only applies on StackOverflow, since
+    /// other sites may well not have it.
+    #[serde(skip)]
+    pub project_tag: Option<String>,
+    pub filters: SearchFilters,
+    /// Strip details particular to a run, e.g. paths, line numbers, and
+    /// addresses, from error messages, whether typed or read from command
+    /// output; other typed queries are left as they are
+    pub normalize_query: bool,
+    /// Search engines to try in order, falling back to the next one when a
+    /// search is blocked or fails to connect
+    #[serde(deserialize_with = "one_or_many")]
+    pub search_engine: Vec<SearchEngine>,
     pub copy_cmd: Option<String>,
+    /// Seconds to wait for a connection to be established
+    pub connect_timeout: u64,
+    /// Seconds to wait on a read from an open connection
+    pub read_timeout: u64,
+    /// Times to retry a failed request
+    pub retries: u32,
+    /// HTTP, HTTPS, or SOCKS proxy URL for all requests; when unset, the
+    /// standard `HTTP_PROXY`, `HTTPS_PROXY`, and `ALL_PROXY` variables are used
+    pub proxy: Option<String>,
+    /// Hosts that bypass the proxy, in addition to those in `NO_PROXY`
+    pub no_proxy: Vec<String>,
+    /// PEM files with extra root certificates to trust
+    pub ca_certs: Vec<PathBuf>,
+    /// StackExchange API URL, e.g. of a caching mirror
+    pub api_url: Option<String>,
+    /// StackExchange API version
+    pub api_version: Option<String>,
+    /// DuckDuckGo search URL
+    pub duckduckgo_url: Option<String>,
+    /// Google search URL
+    pub google_url: Option<String>,
+    /// Bing search URL
+    pub bing_url: Option<String>,
+    /// Brave Search URL
+    pub brave_url: Option<String>,
+    /// Startpage search URL
+    pub startpage_url: Option<String>,
+    /// SearXNG instance URL, required by the `searxng` search engine
+    pub searxng_url: Option<String>,
+    /// User defined search engines
+    pub engines: Vec<CustomEngine>,
 }
 
+/// Environment variables that override the endpoint URLs in the config file
+const API_URL_VAR: &str = "SO_API_URL";
+const API_VERSION_VAR: &str = "SO_API_VERSION";
+const DUCKDUCKGO_URL_VAR: &str = "SO_DUCKDUCKGO_URL";
+const GOOGLE_URL_VAR: &str = "SO_GOOGLE_URL";
+const BING_URL_VAR: &str = "SO_BING_URL";
+const BRAVE_URL_VAR: &str = "SO_BRAVE_URL";
+const STARTPAGE_URL_VAR: &str = "SO_STARTPAGE_URL";
+const SEARXNG_URL_VAR: &str = "SO_SEARXNG_URL";
+
 impl fmt::Display for SearchEngine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match &self {
             SearchEngine::DuckDuckGo => "duckduckgo",
             SearchEngine::Google => "google",
             SearchEngine::StackExchange => "stackexchange",
+            SearchEngine::StackExchangeSimilar => "stackexchange-similar",
+            SearchEngine::StackExchangeExcerpts => "stackexchange-excerpts",
+            SearchEngine::Bing => "bing",
+            SearchEngine::Brave => "brave",
+            SearchEngine::Startpage => "startpage",
+            SearchEngine::Searxng => "searxng",
+            SearchEngine::Meta => "meta",
+            SearchEngine::Custom(name) => name,
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl SearchEngine {
+    /// Names of the built in search engines
+    pub const BUILTIN: &'static [&'static str] = &[
+        "duckduckgo",
+        "google",
+        "bing",
+        "brave",
+        "startpage",
+        "searxng",
+        "stackexchange",
+        "stackexchange-similar",
+        "stackexchange-excerpts",
+        "meta",
+    ];
+}
+
+impl From<String> for SearchEngine {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "duckduckgo" => SearchEngine::DuckDuckGo,
+            "google" => SearchEngine::Google,
+            "stackexchange" => SearchEngine::StackExchange,
+            "stackexchange-similar" => SearchEngine::StackExchangeSimilar,
+            "stackexchange-excerpts" => SearchEngine::StackExchangeExcerpts,
+            "bing" => SearchEngine::Bing,
+            "brave" => SearchEngine::Brave,
+            "startpage" => SearchEngine::Startpage,
+            "searxng" => SearchEngine::Searxng,
+            "meta" => SearchEngine::Meta,
+            _ => SearchEngine::Custom(name),
+        }
+    }
+}
+
+impl From<SearchEngine> for String {
+    fn from(engine: SearchEngine) -> Self {
+        engine.to_string()
+    }
+}
+
+impl SearchSort {
+    /// Names of the sort orders
+    pub const ALL: &'static [&'static str] = &["relevance", "votes", "activity", "creation"];
+}
+
+impl fmt::Display for SearchSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SearchSort::Relevance => "relevance",
+            SearchSort::Votes => "votes",
+            SearchSort::Activity => "activity",
+            SearchSort::Creation => "creation",
         };
         write!(f, "{s}")
     }
 }
 
+impl FromStr for SearchSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relevance" => Ok(SearchSort::Relevance),
+            "votes" => Ok(SearchSort::Votes),
+            "activity" => Ok(SearchSort::Activity),
+            "creation" => Ok(SearchSort::Creation),
+            _ => Err(format!("unknown sort `{s}`")),
+        }
+    }
+}
+
+impl Date {
+    /// Unix epoch seconds at the start of the day (UTC)
+    pub fn start(&self) -> i64 {
+        self.0
+    }
+
+    /// Unix epoch seconds at the end of the day (UTC)
+    pub fn end(&self) -> i64 {
+        self.0 + 86_399
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        utils::parse_date(s)
+            .map(Date)
+            .ok_or_else(|| format!("invalid date `{s}`, expected YYYY-MM-DD"))
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Date> for String {
+    fn from(date: Date) -> Self {
+        utils::format_date(date.0)
+    }
+}
+
+/// Accept either a single search engine, as in older configs, or a list
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<SearchEngine>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(SearchEngine),
+        Many(Vec<SearchEngine>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(engine) => vec![engine],
+        OneOrMany::Many(engines) => engines,
+    })
+}
+
 // TODO make a friender config file, like the colors.toml below
 impl Default for Config {
     fn default() -> Self {
@@ -50,7 +313,12 @@ impl Default for Config {
             limit: 20,
             lucky: true,
             sites: vec![String::from("stackoverflow")],
-            search_engine: SearchEngine::default(),
+            tags: vec![],
+            auto_tag: false,
+            project_tag: None,
+            filters: SearchFilters::default(),
+            normalize_query: true,
+            search_engine: vec![SearchEngine::default()],
             copy_cmd: Some(String::from(if cfg!(target_os = "macos") {
                 "pbcopy"
             } else if cfg!(target_os = "windows") {
@@ -61,6 +329,21 @@ impl Default for Config {
                 // this default makes no sense but w/e
                 "wl-copy"
             })),
+            connect_timeout: 10,
+            read_timeout: 30,
+            retries: 2,
+            proxy: None,
+            no_proxy: vec![],
+            ca_certs: vec![],
+            api_url: None,
+            api_version: None,
+            duckduckgo_url: None,
+            google_url: None,
+            bing_url: None,
+            brave_url: None,
+            startpage_url: None,
+            searxng_url: None,
+            engines: vec![],
         }
     }
 }
@@ -68,6 +351,10 @@ impl Default for Config {
 impl Config {
     /// Get user config (writes default if none found)
     pub fn new() -> Result<Self> {
+        Ok(Self::from_file()?.with_env_overrides(|var| env::var(var).ok()))
+    }
+
+    fn from_file() -> Result<Self> {
         let project = Self::project_dir()?;
         let dir = project.config_dir();
         fs::create_dir_all(dir)?;
@@ -82,7 +369,7 @@ impl Config {
             Some(file) => serde_yaml::from_reader(file)
                 .map_err(|_| Error::MalformedFile(filename.clone()))
                 .and_then(|cfg: Config| {
-                    if cfg.sites.is_empty() {
+                    if cfg.sites.is_empty() || cfg.search_engine.is_empty() {
                         Err(Error::MalformedFile(filename))
                     } else {
                         Ok(cfg)
@@ -91,10 +378,31 @@ impl Config {
         }
     }
 
+    /// Override endpoint URLs with those set in the environment
+    fn with_env_overrides(mut self, env: impl Fn(&str) -> Option<String>) -> Self {
+        let overrides = [
+            (API_URL_VAR, &mut self.api_url),
+            (API_VERSION_VAR, &mut self.api_version),
+            (DUCKDUCKGO_URL_VAR, &mut self.duckduckgo_url),
+            (GOOGLE_URL_VAR, &mut self.google_url),
+            (BING_URL_VAR, &mut self.bing_url),
+            (BRAVE_URL_VAR, &mut self.brave_url),
+            (STARTPAGE_URL_VAR, &mut self.startpage_url),
+            (SEARXNG_URL_VAR, &mut self.searxng_url),
+        ];
+        for (var, field) in overrides {
+            if let Some(value) = env(var).filter(|v| !v.is_empty()) {
+                *field = Some(value);
+            }
+        }
+        self
+    }
+
     // TODO This looks odd when refactoring to associate functions under Config; perhaps this
     // shouldn't be a CLI opt? Maybe a generic --save-config based on current opts?
     pub fn set_api_key(key: String) -> Result<()> {
-        let mut cfg = Self::new()?;
+        // Don't persist environment overrides
+        let mut cfg = Self::from_file()?;
         cfg.api_key = Some(key);
         cfg.write()
     }
@@ -133,3 +441,113 @@ impl Config {
         Some(cmd)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_overrides() {
+        let config = Config {
+            api_url: Some(String::from("http://mirror")),
+            google_url: Some(String::from("http://google")),
+            ..Config::default()
+        }
+        .with_env_overrides(|var| match var {
+            API_URL_VAR => Some(String::from("http://localhost:8080")),
+            DUCKDUCKGO_URL_VAR => Some(String::from("http://localhost:8081")),
+            GOOGLE_URL_VAR => Some(String::new()),
+            _ => None,
+        });
+        assert_eq!(config.api_url.as_deref(), Some("http://localhost:8080"));
+        assert_eq!(config.api_version, None);
+        assert_eq!(
+            config.duckduckgo_url.as_deref(),
+            Some("http://localhost:8081")
+        );
+        assert_eq!(config.google_url.as_deref(), Some("http://google"));
+    }
+
+    #[test]
+    fn test_search_engine_list() {
+        let config: Config = serde_yaml::from_str("search_engine: duckduckgo").unwrap();
+        assert_eq!(config.search_engine, vec![SearchEngine::DuckDuckGo]);
+
+        let config: Config =
+            serde_yaml::from_str("search_engine: [duckduckgo, stackexchange]").unwrap();
+        assert_eq!(
+            config.search_engine,
+            vec![SearchEngine::DuckDuckGo, SearchEngine::StackExchange]
+        );
+
+        let config: Config =
+            serde_yaml::from_str("search_engine: [stackexchange-excerpts, stackexchange-similar]")
+                .unwrap();
+        assert_eq!(
+            config.search_engine,
+            vec![
+                SearchEngine::StackExchangeExcerpts,
+                SearchEngine::StackExchangeSimilar
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+filters:
+  accepted: true
+  min_score: -1
+  from_date: 2020-02-29
+  sort: votes
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.filters,
+            SearchFilters {
+                accepted: true,
+                min_score: Some(-1),
+                from_date: Some("2020-02-29".parse().unwrap()),
+                sort: SearchSort::Votes,
+                ..SearchFilters::default()
+            }
+        );
+        let yaml = serde_yaml::to_string(&config.filters).unwrap();
+        assert!(yaml.contains("from_date: 2020-02-29"));
+        assert!(yaml.contains("sort: votes"));
+
+        let invalid = serde_yaml::from_str::<Config>("filters: {to_date: 2021-02-29}");
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_custom_engines() {
+        let config: Config = serde_yaml::from_str(
+            r##"
+search_engine: [qwant, google]
+engines:
+  - name: qwant
+    url: "https://lite.qwant.com/?q={sites}+{query}&p={page}"
+    selector: "article a.url"
+    blocked: "#captcha"
+"##,
+        )
+        .unwrap();
+        assert_eq!(
+            config.search_engine,
+            vec![
+                SearchEngine::Custom(String::from("qwant")),
+                SearchEngine::Google
+            ]
+        );
+        assert_eq!(config.engines[0].name, "qwant");
+        assert_eq!(config.engines[0].href_attribute, None);
+        assert_eq!(config.engines[0].blocked.as_deref(), Some("#captcha"));
+
+        // Round trips through the config file
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert_eq!(serde_yaml::from_str::<Config>(&yaml).unwrap(), config);
+    }
+}
@@ -13,6 +13,11 @@ pub enum Error {
     IO(#[from] std::io::Error),
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
+    #[error("Request failed after {attempts} attempt(s): {source}")]
+    Http {
+        attempts: u32,
+        source: reqwest::Error,
+    },
     #[error("SerdeJson error: {0}")]
     SerdeJson(#[from] serde_json::Error),
     #[error("SerdeYaml error: {0}")]
@@ -25,8 +30,46 @@ pub enum Error {
     Permissions(PermissionType, PathBuf),
     #[error("{0}")]
     StackExchange(String),
+    #[error("StackExchange API error {id} ({name}): {message}")]
+    StackExchangeApi {
+        id: u32,
+        name: String,
+        message: String,
+    },
+    #[error(
+        "StackExchange is throttling requests: {0}. \
+        Try again later, or set your own API key with `so --set-api-key`"
+    )]
+    Throttled(String),
     #[error("{0}")]
     Scraping(String),
+    #[error(
+        "{0} wants a captcha solved, suspecting automated traffic. \
+        Try again later, or use a different search engine with `--search-engine`"
+    )]
+    Captcha(String),
+    #[error(
+        "{0} wants cookie consent before showing results. \
+        Use a different search engine with `--search-engine`"
+    )]
+    ConsentRequired(String),
+    #[error(
+        "{engine} is rate limiting requests (HTTP {status}). \
+        Try again later, or use a different search engine with `--search-engine`"
+    )]
+    RateLimited { engine: String, status: u16 },
+    #[error("Invalid URL `{0}`")]
+    InvalidUrl(String),
+    #[error("Missing `{0}` in the config file")]
+    MissingConfig(String),
+    #[error("Unknown search engine `{0}`; define it under `engines` in the config file")]
+    UnknownEngine(String),
+    #[error("Search engine `{0}` is misconfigured: {1}")]
+    InvalidEngine(String, String),
+    #[error("Nothing to search for: {0}")]
+    NoQuery(String),
+    #[error("Failed to run `{0}`: {1}")]
+    RunCommand(String, std::io::Error),
     #[error("Couldn't find a suitable project directory; is your OS supported?")]
     ProjectDir,
     #[error("Sorry, couldn't find any answers to your question")]
@@ -1,6 +1,9 @@
 pub mod cli;
 pub mod config;
 pub mod error;
+pub mod project;
+pub mod query;
+pub mod shell;
 pub mod stackexchange;
 pub mod term;
 pub mod tui;
@@ -1,19 +1,27 @@
 mod cli;
 mod config;
 mod error;
+mod project;
+mod query;
+mod shell;
 mod stackexchange;
 mod term;
 mod tui;
 mod utils;
 
+use std::io::Write as _;
+use std::process::Stdio;
 use std::{fmt::Write, sync::Arc};
 
 use crossterm::event::{KeyCode, KeyEvent};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
 use tokio::runtime::Runtime;
 use tokio::task;
 
 use config::Config;
 use error::{Error, Result};
+use shell::LastError;
 use stackexchange::{LocalStorage, Search};
 use term::Term;
 
@@ -44,7 +52,14 @@ async fn run() -> Result<Option<tui::App>> {
     // Term tools and markdown styles (outside of TUI)
     let mut term = Term::new();
 
-    let ls = LocalStorage::new(opts.update_sites).await?;
+    if let Some(shell) = opts.shell_init {
+        print!("{}", shell.init_script(&shell::record_dir()?));
+        return Ok(None);
+    }
+
+    // Whether the query is a line of command output, rather than typed
+    let from_output = opts.run.is_some() || opts.last_error || opts.piped_query;
+    let ls = LocalStorage::new(opts.update_sites, &config).await?;
 
     if let Some(key) = opts.set_api_key {
         Config::set_api_key(key)?;
@@ -77,12 +92,77 @@ async fn run() -> Result<Option<tui::App>> {
         return Ok(None);
     }
 
-    if let Some(q) = opts.query {
+    if let Some(target) = opts.open {
+        let Some((site, link)) = ls.find_post(&target) else {
+            term.print_error(&format!(
+                "{target} is not a recognized StackExchange question or answer link.\n\n"
+            ))?;
+            term.print_notice(
+                "Try passing a full URL, or `site-code:question-id`. \
+                You can run `so --list-sites` to list all available site codes.",
+            )?;
+            return Ok(None);
+        };
+        let config = Config {
+            sites: vec![site.clone()],
+            ..config
+        };
         let site_map = Arc::new(ls.get_site_map(&config.sites));
-        let mut search = Search::new(config.clone(), Arc::clone(&site_map), q);
+        let search = Search::new(config, site_map, String::new())?;
+        return Ok(Some(
+            Term::wrap_spinner(tui::App::from_post(search, &site, link)).await??,
+        ));
+    }
+
+    let query = match opts.run {
+        Some(cmd) => {
+            let Some(stderr) = run_command(&cmd).await? else {
+                // Nothing went wrong
+                return Ok(None);
+            };
+            let query = query::error_line(&stderr).ok_or_else(|| {
+                Error::NoQuery(format!(
+                    "`{}` failed without an error message",
+                    cmd.join(" ")
+                ))
+            })?;
+            Some(query)
+        }
+        None if opts.last_error => {
+            let last = last_error()?;
+            let query = query::error_line(&last.stderr).ok_or_else(|| {
+                Error::NoQuery(format!(
+                    "no error message was recorded for `{cmd}`, which failed with exit \
+                    status {status}; try `so --run -- {cmd}`",
+                    cmd = last.command,
+                    status = last.status,
+                ))
+            })?;
+            Some(query)
+        }
+        None => opts.query,
+    };
+
+    if let Some(q) = query {
+        let q = if !config.normalize_query {
+            q
+        } else if from_output {
+            query::normalize_error(&q)
+        } else {
+            query::normalize(&q)
+        };
+        let site_map = Arc::new(ls.get_site_map(&config.sites));
+        let mut search = Search::new(config.clone(), Arc::clone(&site_map), q)?;
         if lucky {
+            if opts.show_query {
+                term.print_notice(&format!("Searching for `{}`\n\n", search.query))?;
+            }
             // Show top answer
             let lucky_answer = Term::wrap_spinner(search.search_lucky()).await??;
+            for warning in search.take_warnings() {
+                term.print_notice(&format!("{warning}\n\n"))?;
+            }
+            notice_quota(&mut term, &search)?;
             term.print(&lucky_answer.answer.body);
             term.print("\nPress **[SPACE]** to see more results, **[o]** to open in the browser, or any other key to exit");
 
@@ -106,12 +186,60 @@ async fn run() -> Result<Option<tui::App>> {
             }
 
             // Get the rest of the questions
-            return Ok(Some(Term::wrap_spinner(app).await?.unwrap()?));
+            let mut app = Term::wrap_spinner(app).await?.unwrap()?;
+            if opts.show_query {
+                app.show_query();
+            }
+            return Ok(Some(app));
         } else {
-            return Ok(Some(
-                Term::wrap_spinner(tui::App::from_search(search)).await??,
-            ));
+            let quota_search = search.clone();
+            let mut app = Term::wrap_spinner(tui::App::from_search(search)).await??;
+            // Shown in the TUI, which would hide anything printed here
+            if opts.show_query {
+                app.show_query();
+            }
+            quota_search.warn_low_quota();
+            return Ok(Some(app));
         }
     }
     Ok(None)
 }
+
+/// Run a command, passing its stderr through as it goes. Returns the stderr if
+/// the command failed.
+async fn run_command(cmd: &[String]) -> Result<Option<String>> {
+    let (program, args) = cmd.split_first().expect("bug: empty command");
+    let mut child = Command::new(program)
+        .args(args)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::RunCommand(cmd.join(" "), e))?;
+    let mut reader = BufReader::new(child.stderr.take().expect("bug: stderr not piped"));
+    let mut stderr = String::new();
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).await? > 0 {
+        std::io::stderr().write_all(&line)?;
+        stderr.push_str(&String::from_utf8_lossy(&line));
+        line.clear();
+    }
+    let status = child.wait().await?;
+    Ok((!status.success()).then_some(stderr))
+}
+
+/// The last failed command, as recorded by the shell integration
+fn last_error() -> Result<LastError> {
+    LastError::read(&shell::record_dir()?)?.ok_or_else(|| {
+        Error::NoQuery(String::from(
+            "no failed command has been recorded; \
+            add `so --shell-init <shell>` to your shell's startup file",
+        ))
+    })
+}
+
+/// Warn the user if the StackExchange API quota is running low
+fn notice_quota(term: &mut Term, search: &Search) -> Result<()> {
+    if let Some(warning) = search.quota_warning() {
+        term.print_notice(&format!("{warning}.\n\n"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! Infer the StackExchange tag of the project in a directory
+use std::path::Path;
+
+/// Files marking the root of a project, and the tag of its language, in order
+/// of precedence; e.g. a `tsconfig.json` beats the `package.json` beside it
+const MARKERS: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust"),
+    ("tsconfig.json", "typescript"),
+    ("package.json", "javascript"),
+    ("pyproject.toml", "python"),
+    ("setup.py", "python"),
+    ("requirements.txt", "python"),
+    ("Pipfile", "python"),
+    ("go.mod", "go"),
+    ("Gemfile", "ruby"),
+    ("composer.json", "php"),
+    ("pom.xml", "java"),
+    ("build.gradle.kts", "kotlin"),
+    ("build.gradle", "java"),
+    ("build.sbt", "scala"),
+    ("mix.exs", "elixir"),
+    ("stack.yaml", "haskell"),
+    ("cabal.project", "haskell"),
+    ("Package.swift", "swift"),
+    ("pubspec.yaml", "dart"),
+    ("dune-project", "ocaml"),
+    ("Project.toml", "julia"),
+];
+
+/// Tag of the project containing `dir`, looking up through its parents until
+/// a project marker is found. The search stops at the repository root, if any.
+pub fn project_tag(dir: &Path) -> Option<String> {
+    for dir in dir.ancestors() {
+        if let Some(tag) = marker_tag(|file| dir.join(file).exists()) {
+            return Some(tag.to_string());
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Tag of the highest precedence marker that exists
+fn marker_tag(exists: impl Fn(&str) -> bool) -> Option<&'static str> {
+    MARKERS
+        .iter()
+        .find(|(file, _)| exists(file))
+        .map(|(_, tag)| *tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker_tag() {
+        let in_dir = |files: &'static [&'static str]| move |file: &str| files.contains(&file);
+        assert_eq!(marker_tag(in_dir(&["pyproject.toml"])), Some("python"));
+        assert_eq!(
+            marker_tag(in_dir(&["package.json", "tsconfig.json"])),
+            Some("typescript")
+        );
+        assert_eq!(
+            marker_tag(in_dir(&["package.json", "Cargo.toml"])),
+            Some("rust")
+        );
+        assert_eq!(marker_tag(in_dir(&["README.md"])), None);
+    }
+
+    #[test]
+    fn test_project_tag() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(project_tag(root), Some(String::from("rust")));
+        assert_eq!(project_tag(&root.join("src")), Some(String::from("rust")));
+    }
+}
@@ -0,0 +1,366 @@
+//! Normalize pasted compiler and runtime errors into search queries
+//!
+//! Error output is full of details particular to the machine and the run, e.g.
+//! absolute paths, line and column numbers, memory addresses, timestamps, and
+//! PIDs, which only get in the way of finding others with the same error. These
+//! are stripped or generalized, leaving the error code and message skeleton.
+
+/// Words followed by a number particular to the run, dropped along with it
+const NUMBERED: &[&str] = &["line", "column", "col", "pid", "tid", "thread"];
+
+/// Punctuation wrapping a token, kept around its normalized form
+const OPENERS: &[char] = &['"', '\'', '`', '(', '[', '{', '<'];
+const CLOSERS: &[char] = &['"', '\'', '`', ')', ']', '}', '>', ',', ';', ':', '.'];
+
+/// Markers of the line stating an error, as opposed to context around it
+const ERROR_MARKERS: &[&str] = &[
+    "error[",
+    "error:",
+    "Error:",
+    "ERROR",
+    "Exception",
+    "exception:",
+    "panicked at",
+    "fatal:",
+    "FATAL",
+    "Fatal error",
+];
+
+/// Lines that mention errors without stating one, e.g. summaries
+const ERROR_NOISE: &[&str] = &[
+    "could not compile",
+    "aborting due to",
+    "For more information about this error",
+    "Traceback (most recent call last)",
+    "During handling of the above exception",
+];
+
+/// The most relevant line of some error output: the first line stating an
+/// error, else the first mentioning one, else the last line. A line ending in
+/// a colon is joined with the next, which holds the message, as in Rust panics.
+pub fn error_line(output: &str) -> Option<String> {
+    let lines: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let is_noise = |line: &&str| ERROR_NOISE.iter().any(|noise| line.contains(noise));
+    let position = lines
+        .iter()
+        .position(|line| !is_noise(line) && ERROR_MARKERS.iter().any(|m| line.contains(m)))
+        .or_else(|| {
+            lines.iter().position(|line| {
+                let lower = line.to_lowercase();
+                !is_noise(line)
+                    && ["error", "fail", "exception"]
+                        .iter()
+                        .any(|word| lower.contains(word))
+            })
+        })
+        .or_else(|| lines.len().checked_sub(1))?;
+    let line = lines[position];
+    match lines.get(position + 1) {
+        Some(next) if line.ends_with(':') => Some(format!("{line} {next}")),
+        _ => Some(line.to_string()),
+    }
+}
+
+/// Normalize a query if it states an error, e.g. one pasted from compiler
+/// output. Plain queries are left as they are, save for whitespace, since
+/// their numbers and brackets may well matter, as in "go to line 10".
+pub fn normalize(query: &str) -> String {
+    if ERROR_MARKERS.iter().any(|marker| query.contains(marker)) {
+        normalize_error(query)
+    } else {
+        query.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Normalize an error message into a search query, e.g. a line of a failed
+/// command's output
+pub fn normalize_error(query: &str) -> String {
+    let mut words = Vec::new();
+    let mut tokens = query.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        let (open, core, close) = split_punctuation(token);
+        if NUMBERED.contains(&core.to_lowercase().as_str())
+            && tokens
+                .peek()
+                .is_some_and(|next| is_number(split_punctuation(next).1))
+        {
+            tokens.next();
+            continue;
+        }
+        if let Some(core) = generalize(core) {
+            words.push(format!("{open}{core}{close}"));
+        }
+    }
+    if words.is_empty() {
+        query.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        words.join(" ")
+    }
+}
+
+/// Split a token into its leading punctuation, core, and trailing punctuation
+fn split_punctuation(token: &str) -> (&str, &str, &str) {
+    let core = token.trim_start_matches(OPENERS);
+    let open = &token[..token.len() - core.len()];
+    let trimmed = core.trim_end_matches(CLOSERS);
+    // Keep a closing bracket or quote that pairs with one inside, e.g. `error[E0382]`
+    let core = match core[trimmed.len()..].chars().next() {
+        Some(c @ (']' | ')' | '}' | '>')) if trimmed.contains(opener(c)) => {
+            &core[..trimmed.len() + 1]
+        }
+        _ => trimmed,
+    };
+    let close = &token[open.len() + core.len()..];
+    (open, core, close)
+}
+
+fn opener(closer: char) -> char {
+    match closer {
+        ']' => '[',
+        ')' => '(',
+        '}' => '{',
+        _ => '<',
+    }
+}
+
+/// The normalized form of a token's core, or `None` to drop it
+fn generalize(core: &str) -> Option<String> {
+    if core.is_empty() {
+        return Some(String::new());
+    }
+    if is_hex_address(core) || is_hash(core) || is_timestamp(core) || is_pid(core) {
+        return None;
+    }
+    let core = strip_position(core);
+    if core.is_empty() {
+        return None;
+    }
+    if is_path(core) {
+        let name = core.rsplit(['/', '\\']).next().unwrap_or(core);
+        return (!name.is_empty()).then(|| name.to_string());
+    }
+    Some(strip_bracketed_number(core).to_string())
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+/// E.g. `0x7ffee3b8a9c0`
+fn is_hex_address(s: &str) -> bool {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"));
+    digits.is_some_and(|d| !d.is_empty() && d.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Commit hashes, UUIDs, and the like
+fn is_hash(s: &str) -> bool {
+    let hex: String = s.chars().filter(|c| *c != '-').collect();
+    hex.len() >= 12
+        && hex.chars().all(|c| c.is_ascii_hexdigit())
+        && hex.chars().any(|c| c.is_ascii_digit())
+        && hex.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// Dates like `2024-07-01`, possibly with a time, and times like `12:34:56.789`
+fn is_timestamp(s: &str) -> bool {
+    let b = s.as_bytes();
+    let is_date = b.len() >= 10
+        && b[..4].iter().all(u8::is_ascii_digit)
+        && b[4] == b'-'
+        && b[5..7].iter().all(u8::is_ascii_digit)
+        && b[7] == b'-'
+        && b[8..10].iter().all(u8::is_ascii_digit);
+    let is_time = s.contains(':')
+        && s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars().all(|c| c.is_ascii_digit() || ":.,".contains(c));
+    is_date || is_time
+}
+
+/// E.g. `pid=1234` or `PID:1234`
+fn is_pid(s: &str) -> bool {
+    let lower = s.to_lowercase();
+    ["pid=", "pid:", "tid=", "tid:"]
+        .iter()
+        .any(|prefix| lower.strip_prefix(prefix).is_some_and(is_number))
+}
+
+/// Strip a trailing source position, e.g. `:10:5` or `(10,5)`
+fn strip_position(s: &str) -> &str {
+    let mut s = s;
+    if let Some(inner) = s.strip_suffix(')') {
+        if let Some((rest, position)) = inner.rsplit_once('(') {
+            if !position.is_empty() && position.split(',').all(is_number) {
+                s = rest;
+            }
+        }
+    }
+    while let Some((rest, position)) = s.rsplit_once(':') {
+        if !is_number(position) {
+            break;
+        }
+        s = rest;
+    }
+    s
+}
+
+/// Whether a token looks like a file path, rather than e.g. "and/or"
+fn is_path(s: &str) -> bool {
+    if s.contains("://") || !(s.contains('/') || s.contains('\\')) {
+        return false;
+    }
+    let rooted = ["/", "./", "../", "~/", ".\\", "..\\", "\\\\"]
+        .iter()
+        .any(|prefix| s.starts_with(prefix));
+    let drive = s.len() > 2 && s.as_bytes()[0].is_ascii_alphabetic() && s[1..].starts_with(":\\");
+    let name = s.rsplit(['/', '\\']).next().unwrap_or_default();
+    let has_extension = name
+        .rsplit_once('.')
+        .is_some_and(|(stem, ext)| !stem.is_empty() && !ext.is_empty());
+    rooted || drive || has_extension
+}
+
+/// Strip a process id from e.g. `python[1234]`
+fn strip_bracketed_number(s: &str) -> &str {
+    s.strip_suffix(']')
+        .and_then(|inner| inner.rsplit_once('['))
+        .filter(|(rest, n)| !rest.is_empty() && is_number(n))
+        .map_or(s, |(rest, _)| rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_queries() {
+        for query in [
+            "how do I reverse a list in python",
+            "error[E0382]: borrow of moved value: `x`",
+            "input/output error on exit code 137",
+            "what is 0x in C++?",
+            "vim go to line 10",
+            "python list[0] out of range",
+            "why does print(1,2) print a tuple",
+            "cron job at 10:30 not running",
+            "git checkout 3f2a9c1b7e4d5a6b",
+        ] {
+            assert_eq!(normalize(query), query);
+        }
+        assert_eq!(normalize("  how do i\n exit vim "), "how do i exit vim");
+    }
+
+    #[test]
+    fn test_compiler_errors() {
+        assert_eq!(
+            normalize_error(
+                "error[E0382]: borrow of moved value: `v`\n --> /home/me/proj/src/main.rs:4:18"
+            ),
+            "error[E0382]: borrow of moved value: `v` --> main.rs"
+        );
+        assert_eq!(
+            normalize_error("/tmp/x/main.c:10:5: error: expected ';' before '}' token"),
+            "main.c: error: expected ';' before '}' token"
+        );
+        assert_eq!(
+            normalize_error(r"C:\src\App\Program.cs(12,7): error CS1002: ; expected"),
+            "Program.cs: error CS1002: ; expected"
+        );
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        assert_eq!(
+            normalize_error(
+                r#"File "/usr/lib/python3.11/json/decoder.py", line 355, in raw_decode"#
+            ),
+            r#"File "decoder.py", in raw_decode"#
+        );
+        assert_eq!(
+            normalize_error(
+                "thread 'main' panicked at src/main.rs:2:5:\nattempt to divide by zero"
+            ),
+            "thread 'main' panicked at main.rs: attempt to divide by zero"
+        );
+        assert_eq!(
+            normalize_error(
+                "2024-07-01T12:34:56Z 12:34:56.789 app[4242]: segfault at 0x7ffee3b8a9c0 pid=4242"
+            ),
+            "app: segfault at"
+        );
+        assert_eq!(
+            normalize_error("fatal: reference is not a tree: 3f2a9c1b7e4d5a6b"),
+            "fatal: reference is not a tree:"
+        );
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        assert_eq!(
+            normalize("/tmp/x/main.c:10:5: error: expected ';' before '}' token"),
+            "main.c: error: expected ';' before '}' token"
+        );
+        assert_eq!(
+            normalize("fatal: reference is not a tree: 3f2a9c1b7e4d5a6b"),
+            "fatal: reference is not a tree:"
+        );
+    }
+
+    #[test]
+    fn test_error_line() {
+        let cargo = "   Compiling app v0.1.0 (/home/me/app)
+error[E0382]: borrow of moved value: `v`
+ --> src/main.rs:4:18
+  |
+error: aborting due to 1 previous error
+
+For more information about this error, try `rustc --explain E0382`.
+error: could not compile `app` (bin \"app\") due to 1 previous error
+";
+        assert_eq!(
+            error_line(cargo).as_deref(),
+            Some("error[E0382]: borrow of moved value: `v`")
+        );
+
+        let python = r#"Traceback (most recent call last):
+  File "/home/me/app.py", line 1, in <module>
+    int("x")
+ValueError: invalid literal for int() with base 10: 'x'
+"#;
+        assert_eq!(
+            error_line(python).as_deref(),
+            Some("ValueError: invalid literal for int() with base 10: 'x'")
+        );
+
+        let panic = "thread 'main' panicked at src/main.rs:2:5:\nattempt to divide by zero\n\
+            note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace";
+        assert_eq!(
+            error_line(panic).as_deref(),
+            Some("thread 'main' panicked at src/main.rs:2:5: attempt to divide by zero")
+        );
+
+        assert_eq!(
+            error_line("ls: cannot access 'x': No such file or directory\n").as_deref(),
+            Some("ls: cannot access 'x': No such file or directory")
+        );
+        assert_eq!(
+            error_line("npm error code ENOENT\nnpm error syscall open").as_deref(),
+            Some("npm error code ENOENT")
+        );
+        assert_eq!(error_line("\n  \n"), None);
+    }
+
+    #[test]
+    fn test_split_punctuation() {
+        assert_eq!(split_punctuation("`x`,"), ("`", "x", "`,"));
+        assert_eq!(
+            split_punctuation("error[E0382]:"),
+            ("", "error[E0382]", ":")
+        );
+        assert_eq!(split_punctuation("(foo)"), ("(", "foo", ")"));
+        assert_eq!(split_punctuation("\"a.py\","), ("\"", "a.py", "\","));
+    }
+}
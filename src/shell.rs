@@ -0,0 +1,193 @@
+//! Shell integration recording the last failed command, for `so --last-error`
+//!
+//! The snippets printed by `so --shell-init` tee the shell's stderr into a file
+//! under the cache directory, and after each failed command write its exit
+//! status and command line to another, followed by the tail of its stderr.
+//! fish can't redirect its own stderr, so there the stderr is left empty.
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+
+/// Bytes of stderr to record, enough for the error behind a wall of output
+const STDERR_TAIL: usize = 16384;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Names of the supported shells
+    pub const ALL: &'static [&'static str] = &["bash", "zsh", "fish"];
+
+    /// The snippet to evaluate in the shell's startup file, recording failed
+    /// commands under `dir`
+    pub fn init_script(self, dir: &Path) -> String {
+        let script = match self {
+            Shell::Bash => include_str!("../shell/so.bash"),
+            Shell::Zsh => include_str!("../shell/so.zsh"),
+            Shell::Fish => include_str!("../shell/so.fish"),
+        };
+        script
+            .replace("__SO_DIR__", &quote(&dir.to_string_lossy()))
+            .replace("__SO_TAIL__", &STDERR_TAIL.to_string())
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("unsupported shell `{s}`")),
+        }
+    }
+}
+
+/// Single quote a string for bash, zsh, and fish alike
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Directory the shell integration records failed commands in
+pub fn record_dir() -> Result<PathBuf> {
+    Ok(Config::project_dir()?.cache_dir().to_path_buf())
+}
+
+/// The last failed command, as recorded by the shell integration
+#[derive(Debug, PartialEq, Eq)]
+pub struct LastError {
+    pub status: i32,
+    pub command: String,
+    /// Tail of the command's stderr; empty when the shell can't capture it
+    pub stderr: String,
+}
+
+impl LastError {
+    /// Read the last failed command, if any has been recorded
+    pub fn read(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join("last-error");
+        match fs::read(&path) {
+            Ok(bytes) => Self::parse(&String::from_utf8_lossy(&bytes))
+                .map(Some)
+                .ok_or(Error::MalformedFile(path)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut parts = contents.splitn(3, '\n');
+        let status = parts.next()?.trim().parse().ok()?;
+        let command = parts.next()?.trim().to_string();
+        let stderr = clean(parts.next().unwrap_or_default());
+        (!command.is_empty()).then_some(LastError {
+            status,
+            command,
+            stderr,
+        })
+    }
+}
+
+/// Strip what the terminal would hide or overwrite from captured stderr, e.g.
+/// colors and progress bars
+fn clean(stderr: &str) -> String {
+    stderr
+        .lines()
+        .map(|line| {
+            let line = strip_escapes(line);
+            match line.trim_end_matches('\r').rsplit_once('\r') {
+                Some((_, shown)) => shown.to_string(),
+                None => line,
+            }
+        })
+        .skip_while(|line| line.trim().is_empty())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// Strip ANSI escape sequences, e.g. colors
+fn strip_escapes(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+        } else if chars.next() == Some('[') {
+            // Control sequences end with a byte in `@` to `~`
+            chars.by_ref().find(|c| ('@'..='~').contains(c));
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            LastError::parse("101\ncd app && cargo build\nerror[E0382]: borrow of moved value\n"),
+            Some(LastError {
+                status: 101,
+                command: String::from("cd app && cargo build"),
+                stderr: String::from("error[E0382]: borrow of moved value\n"),
+            })
+        );
+        // fish records no stderr
+        assert_eq!(
+            LastError::parse("1\nfalse\n"),
+            Some(LastError {
+                status: 1,
+                command: String::from("false"),
+                stderr: String::new(),
+            })
+        );
+        assert_eq!(
+            LastError::parse(
+                "2\nls /x\n\n\x1b[31mls: cannot access '/x'\x1b[0m\nfetching 10%\rfetching 100%\n"
+            )
+            .unwrap()
+            .stderr,
+            "ls: cannot access '/x'\nfetching 100%\n"
+        );
+        assert_eq!(LastError::parse(""), None);
+        assert_eq!(LastError::parse("oops\ncargo build\n"), None);
+        assert_eq!(LastError::parse("1\n\nerror: oops\n"), None);
+    }
+
+    #[test]
+    fn test_init_script() {
+        let dir = Path::new("/home/me/it's cache");
+        for name in Shell::ALL {
+            let shell: Shell = name.parse().unwrap();
+            assert_eq!(shell.to_string(), *name);
+            let script = shell.init_script(dir);
+            assert!(script.contains(r"'/home/me/it'\''s cache'"), "{}", name);
+            assert!(!script.contains("__SO_"), "{}", name);
+        }
+        assert!("powershell".parse::<Shell>().is_err());
+    }
+}
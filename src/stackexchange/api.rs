@@ -1,14 +1,22 @@
 use rayon::prelude::*;
 use reqwest::header;
-use reqwest::Client;
 use reqwest::Url;
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio::time::{self, Instant};
 
-use crate::error::Result;
+use crate::config::{Config, SearchEngine, SearchFilters, SearchSort};
+use crate::error::{Error, Result};
 use crate::tui::markdown;
 
-/// StackExchange API v2.2 URL
+use super::http;
+
+/// Default StackExchange API v2.2 URL
 const SE_API_URL: &str = "https://api.stackexchange.com";
 const SE_API_VERSION: &str = "2.2";
 
@@ -17,11 +25,28 @@ const SE_API_VERSION: &str = "2.2";
 /// [create filter](https://api.stackexchange.com/docs/create-filter).
 const SE_FILTER: &str = ".DND5X2VHHUH8HyJzpjo)5NvdHI3w6auG";
 
+/// Post metadata fields added to `SE_FILTER`. The resulting filter is created
+/// on demand, see [`Api::create_filter`].
+const SE_FILTER_INCLUDE: &str = "question.tags;question.owner;question.creation_date;\
+    question.last_edit_date;question.view_count;question.closed_reason;answer.owner;\
+    answer.creation_date;answer.last_edit_date;shallow_user.display_name;\
+    shallow_user.reputation";
+
+/// Fields added to the default comment filter. The resulting filter is created
+/// on demand, see [`Api::create_filter`].
+const SE_COMMENT_FILTER_INCLUDE: &str = "comment.body_markdown";
+
 /// Pagesize when fetching all SE sites. Should be good for many years...
 const SE_SITES_PAGESIZE: u16 = 10000;
 
+/// Max number of ids accepted by vectorized SE endpoints, e.g. /questions/{ids}
+const SE_MAX_IDS: usize = 100;
+
 pub type Id = u32;
 
+/// Site code and id of a post, since ids are only unique within a site
+pub type PostKey = (String, Id);
+
 /// Represents a StackExchange answer with a custom selection of fields from
 /// the [StackExchange docs](https://api.stackexchange.com/docs/types/answer)
 #[derive(Clone, Deserialize, Debug)]
@@ -32,6 +57,14 @@ pub struct Answer<S> {
     #[serde(rename = "body_markdown")]
     pub body: S,
     pub is_accepted: bool,
+    // N.B. metadata is optional, in case the metadata filter is unavailable
+    pub owner: Option<User>,
+    /// Unix epoch seconds
+    pub creation_date: Option<i64>,
+    /// Unix epoch seconds
+    pub last_edit_date: Option<i64>,
+    #[serde(default = "Vec::new")]
+    pub comments: Vec<Comment<S>>,
 }
 
 /// Represents a StackExchange question with a custom selection of fields from
@@ -51,12 +84,107 @@ pub struct Question<S> {
     // This is the only field that doesn't actually come back from SE; we add
     // this site code to which the question belongs
     pub site: Option<String>,
+    // Likewise added by us: the engines that surfaced this question, when
+    // results from several search engines are merged
+    #[serde(skip)]
+    pub engines: Vec<SearchEngine>,
+    // Likewise added by us: the answer a search result linked to, if any
+    #[serde(skip)]
+    pub linked_answer: Option<Id>,
+    // Likewise added by us: the snippet that matched a full text search, with
+    // the matching terms emphasized
+    #[serde(skip)]
+    pub excerpt: Option<S>,
+    // N.B. metadata is optional, in case the metadata filter is unavailable
+    #[serde(default = "Vec::new")]
+    pub tags: Vec<String>,
+    pub owner: Option<User>,
+    /// Unix epoch seconds
+    pub creation_date: Option<i64>,
+    /// Unix epoch seconds
+    pub last_edit_date: Option<i64>,
+    pub view_count: Option<u32>,
+    /// Only present on closed questions, e.g. "duplicate"
+    pub closed_reason: Option<String>,
+    // Comments come from a separate endpoint
+    #[serde(default = "Vec::new")]
+    pub comments: Vec<Comment<S>>,
+}
+
+impl<S> Question<S> {
+    /// Site and id of the question. Panics if the site was not attached.
+    pub fn key(&self) -> PostKey {
+        let site = self
+            .site
+            .clone()
+            .expect("bug: site not attached to question");
+        (site, self.id)
+    }
+}
+
+/// Represents a StackExchange comment with a custom selection of fields from
+/// the [StackExchange docs](https://api.stackexchange.com/docs/types/comment)
+#[derive(Clone, Deserialize, Debug)]
+pub struct Comment<S> {
+    #[serde(rename = "comment_id")]
+    pub id: Id,
+    pub post_id: Id,
+    pub score: i32,
+    #[serde(rename = "body_markdown")]
+    pub body: S,
+    // Missing for deleted users
+    pub owner: Option<User>,
+    /// Unix epoch seconds
+    pub creation_date: i64,
+}
+
+/// Represents a StackExchange shallow user with a custom selection of fields from
+/// the [StackExchange docs](https://api.stackexchange.com/docs/types/shallow-user)
+#[derive(Clone, Deserialize, Debug)]
+pub struct User {
+    pub display_name: Option<String>,
+    pub reputation: Option<u32>,
 }
 
 /// Internal struct that represents the boilerplate response wrapper from SE API.
+/// See the [wrapper docs](https://api.stackexchange.com/docs/wrapper).
 #[derive(Deserialize, Debug)]
 struct ResponseWrapper<T> {
+    // Error responses have no items
+    #[serde(default = "Vec::new")]
     items: Vec<T>,
+    #[serde(default)]
+    has_more: bool,
+    error_id: Option<u32>,
+    error_name: Option<String>,
+    error_message: Option<String>,
+    /// Seconds to wait before hitting the same method again
+    backoff: Option<u64>,
+    quota_remaining: Option<u32>,
+}
+
+/// SE error id for throttle violations, see [errors](https://api.stackexchange.com/docs/error-handling)
+const SE_THROTTLE_VIOLATION: u32 = 502;
+
+/// Internal struct for resolving an answer's parent question
+#[derive(Deserialize, Debug)]
+struct AnswerParent {
+    answer_id: Id,
+    question_id: Id,
+}
+
+/// Internal struct for a /search/excerpts result, which is either a question
+/// or an answer
+#[derive(Deserialize, Debug)]
+struct Excerpt {
+    question_id: Id,
+    answer_id: Option<Id>,
+    excerpt: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Filter {
+    filter: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -68,11 +196,20 @@ pub struct Site {
 #[derive(Debug, Clone)]
 pub struct Api {
     client: Client,
+    /// API URL, including the version
+    base_url: Url,
     api_key: Option<String>,
+    retries: u32,
+    filter: Arc<OnceCell<String>>,
+    comment_filter: Arc<OnceCell<String>>,
+    /// No requests should be issued before this instant, per the API's `backoff`
+    backoff_until: Arc<Mutex<Option<Instant>>>,
+    /// Quota remaining as of the latest response
+    quota_remaining: Arc<Mutex<Option<u32>>>,
 }
 
 impl Api {
-    pub fn new(api_key: Option<String>) -> Self {
+    pub fn new(config: &Config) -> Result<Self> {
         // TODO can lazy_static this above
         let mut headers = header::HeaderMap::new();
         headers.insert(
@@ -83,8 +220,37 @@ impl Api {
             header::USER_AGENT,
             header::HeaderValue::from_static(super::USER_AGENT),
         );
-        let client = Client::builder().default_headers(headers).build().unwrap();
-        Api { client, api_key }
+        let client = http::client_builder(config)?
+            .default_headers(headers)
+            .build()?;
+        let mut base_url = http::base_url(config.api_url.as_deref().unwrap_or(SE_API_URL))?;
+        base_url
+            .path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .push(config.api_version.as_deref().unwrap_or(SE_API_VERSION));
+        Ok(Api {
+            client,
+            base_url,
+            api_key: config.api_key.clone(),
+            retries: config.retries,
+            filter: Arc::new(OnceCell::new()),
+            comment_filter: Arc::new(OnceCell::new()),
+            backoff_until: Arc::new(Mutex::new(None)),
+            quota_remaining: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Creates stackexchange API url given endpoint
+    fn url(&self, path: &str) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut().unwrap().extend(path.split('/'));
+        url
+    }
+
+    /// Requests remaining in the daily quota, as of the latest response
+    pub fn quota_remaining(&self) -> Option<u32> {
+        *self.quota_remaining.lock().unwrap()
     }
 
     /// Search against the SE site's /questions/{ids} endpoint.
@@ -92,19 +258,16 @@ impl Api {
     pub async fn questions(&self, site: &str, ids: Vec<String>) -> Result<Vec<Question<String>>> {
         let total = ids.len().to_string();
         let endpoint = format!("questions/{ids}", ids = ids.join(";"));
-        let url = stackexchange_url(&endpoint);
+        let url = self.url(&endpoint);
         log::debug!("Fetching questions from: {url}");
-        let qs_rsp = self
+        let request = self
             .client
             .get(url)
-            .query(&self.get_default_se_opts())
-            .query(&[("site", site), ("pagesize", &total)])
-            .send()
-            .await?;
-        let status_code = qs_rsp.status();
-        let body = qs_rsp.text().await?;
-        log::debug!("Stack exchange returned status {status_code} and body {body}");
-        let qs = serde_json::from_str::<ResponseWrapper<Question<String>>>(&body)?
+            .query(&self.get_default_se_opts().await)
+            .query(&[("site", site), ("pagesize", &total), ("page", "1")]);
+        let qs = self
+            .get::<Question<String>>(request)
+            .await?
             .items
             .into_iter()
             .filter(|q| !q.answers.is_empty())
@@ -112,44 +275,224 @@ impl Api {
         Ok(Self::preprocess(site, qs))
     }
 
+    /// Get the parent question ids of answers from the SE site's /answers/{ids} endpoint.
+    /// Returns a mapping of answer id to question id.
+    pub async fn answer_parents(
+        &self,
+        site: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, String>> {
+        let endpoint = format!("answers/{ids}", ids = ids.join(";"));
+        let mut params = self.get_default_se_opts().await;
+        params.insert("filter", "default");
+        let request = self
+            .client
+            .get(self.url(&endpoint))
+            .query(&params)
+            .query(&[("site", site), ("pagesize", &ids.len().to_string())]);
+        let parents = self.get::<AnswerParent>(request).await?.items;
+        Ok(parents
+            .into_iter()
+            .map(|p| (p.answer_id.to_string(), p.question_id.to_string()))
+            .collect())
+    }
+
     /// Search against the SE site's /search/advanced endpoint with a given query.
-    /// Only fetches questions that have at least one answer.
+    /// Only fetches questions that have at least one answer, all of `tags`, and
+    /// pass `filters`, except for a minimum score when not sorting by votes.
+    /// Pages are 1-indexed, each of size `limit`.
     pub async fn search_advanced(
         &self,
         query: &str,
         site: &str,
+        tags: &[String],
+        filters: &SearchFilters,
         limit: u16,
+        page: u16,
     ) -> Result<Vec<Question<String>>> {
-        let qs = self
+        let request = self
             .client
-            .get(stackexchange_url("search/advanced"))
-            .query(&self.get_default_se_opts())
+            .get(self.url("search/advanced"))
+            .query(&self.get_default_se_opts().await)
             .query(&[
-                ("q", query),
+                (query_param(filters), query),
                 ("pagesize", &limit.to_string()),
+                ("page", &page.to_string()),
                 ("site", site),
                 ("answers", "1"),
                 ("order", "desc"),
-                ("sort", "relevance"),
             ])
-            .send()
-            .await?
-            .json::<ResponseWrapper<Question<String>>>()
-            .await?
-            .items;
+            .query(&tagged(tags))
+            .query(&filter_params(filters))
+            .query(&state_params(filters));
+        let qs = self.get::<Question<String>>(request).await?.items;
         Ok(Self::preprocess(site, qs))
     }
 
-    pub async fn sites(&self) -> Result<Vec<Site>> {
-        let sites = self
+    /// Search against the SE site's /similar endpoint, which matches the query
+    /// against question titles. The endpoint cannot require answers, nor filter
+    /// on accepted answers or closed questions, so unanswered questions are
+    /// dropped, and pages may come up short.
+    pub async fn similar(
+        &self,
+        query: &str,
+        site: &str,
+        tags: &[String],
+        filters: &SearchFilters,
+        limit: u16,
+        page: u16,
+    ) -> Result<Vec<Question<String>>> {
+        let request = self
             .client
-            .get(stackexchange_url("sites"))
-            .query(&[("pagesize", SE_SITES_PAGESIZE.to_string())])
-            .send()
+            .get(self.url("similar"))
+            .query(&self.get_default_se_opts().await)
+            .query(&[
+                ("title", query),
+                ("pagesize", &limit.to_string()),
+                ("page", &page.to_string()),
+                ("site", site),
+                ("order", "desc"),
+            ])
+            .query(&tagged(tags))
+            .query(&filter_params(filters));
+        let qs = self
+            .get::<Question<String>>(request)
             .await?
-            .json::<ResponseWrapper<Site>>()
+            .items
+            .into_iter()
+            .filter(|q| !q.answers.is_empty())
+            .collect();
+        Ok(Self::preprocess(site, qs))
+    }
+
+    /// Search against the SE site's /search/excerpts endpoint, a full text
+    /// search over questions and answers, then fetch the matching questions.
+    /// Each question gets the excerpt that matched it; if that came from an
+    /// answer, the answer is recorded as the question's linked answer.
+    pub async fn search_excerpts(
+        &self,
+        query: &str,
+        site: &str,
+        tags: &[String],
+        filters: &SearchFilters,
+        limit: u16,
+        page: u16,
+    ) -> Result<Vec<Question<String>>> {
+        let mut params = self.get_default_se_opts().await;
+        params.insert("filter", "default");
+        let request = self
+            .client
+            .get(self.url("search/excerpts"))
+            .query(&params)
+            .query(&[
+                (query_param(filters), query),
+                ("pagesize", &limit.to_string()),
+                ("page", &page.to_string()),
+                ("site", site),
+                ("answers", "1"),
+                ("order", "desc"),
+            ])
+            .query(&tagged(tags))
+            .query(&filter_params(filters))
+            .query(&state_params(filters));
+        let excerpts = self.get::<Excerpt>(request).await?.items;
+        // A question can match along with several of its answers; the best
+        // ranked match wins
+        let mut matches: HashMap<Id, (usize, Excerpt)> = HashMap::new();
+        let mut ids = Vec::new();
+        for excerpt in excerpts {
+            if !matches.contains_key(&excerpt.question_id) {
+                ids.push(excerpt.question_id.to_string());
+                matches.insert(excerpt.question_id, (matches.len(), excerpt));
+            }
+        }
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut qs = self.questions(site, ids).await?;
+        for q in qs.iter_mut() {
+            if let Some((_, excerpt)) = matches.get(&q.id) {
+                q.excerpt = Some(markdown::from_excerpt(&excerpt.excerpt));
+                q.linked_answer = excerpt.answer_id;
+            }
+        }
+        qs.sort_by_key(|q| matches.get(&q.id).map(|(rank, _)| *rank));
+        Ok(qs)
+    }
+
+    /// Fetch comments on the given questions from the SE site's
+    /// /questions/{ids}/comments endpoint.
+    pub async fn question_comments(&self, site: &str, ids: &[Id]) -> Result<Vec<Comment<String>>> {
+        self.comments("questions", site, ids).await
+    }
+
+    /// Fetch comments on the given answers from the SE site's
+    /// /answers/{ids}/comments endpoint.
+    pub async fn answer_comments(&self, site: &str, ids: &[Id]) -> Result<Vec<Comment<String>>> {
+        self.comments("answers", site, ids).await
+    }
+
+    /// Fetch all comments on posts of type `posts`, oldest first
+    async fn comments(&self, posts: &str, site: &str, ids: &[Id]) -> Result<Vec<Comment<String>>> {
+        let filter = self
+            .comment_filter
+            .get_or_try_init(|| self.create_filter("default", SE_COMMENT_FILTER_INCLUDE))
+            .await?;
+        let mut params = self.get_default_se_opts().await;
+        params.insert("filter", filter);
+        let mut comments = Vec::new();
+        for chunk in ids.chunks(SE_MAX_IDS) {
+            let ids = chunk.iter().map(Id::to_string).collect::<Vec<_>>();
+            let endpoint = format!("{posts}/{ids}/comments", ids = ids.join(";"));
+            let mut page = 1;
+            loop {
+                let request = self.client.get(self.url(&endpoint)).query(&params).query(&[
+                    ("site", site),
+                    ("pagesize", "100"),
+                    ("page", &page.to_string()),
+                    ("order", "asc"),
+                    ("sort", "creation"),
+                ]);
+                let rsp = self.get::<Comment<String>>(request).await?;
+                comments.extend(rsp.items);
+                if !rsp.has_more {
+                    break;
+                }
+                page += 1;
+            }
+        }
+        Ok(comments
+            .into_iter()
+            .map(|c| Comment {
+                body: markdown::preprocess(c.body),
+                ..c
+            })
+            .collect())
+    }
+
+    /// Create a filter with `include` fields added to the `base` filter. See
+    /// [create filter](https://api.stackexchange.com/docs/create-filter).
+    async fn create_filter(&self, base: &str, include: &str) -> Result<String> {
+        let request = self.client.get(self.url("filters/create")).query(&[
+            ("base", base),
+            ("include", include),
+            ("unsafe", "false"),
+        ]);
+        self.get::<Filter>(request)
             .await?
-            .items;
+            .items
+            .into_iter()
+            .next()
+            .map(|f| f.filter)
+            .ok_or_else(|| Error::StackExchange(String::from("Failed to create API filter")))
+    }
+
+    pub async fn sites(&self) -> Result<Vec<Site>> {
+        let request = self
+            .client
+            .get(self.url("sites"))
+            .query(&[("pagesize", SE_SITES_PAGESIZE.to_string())]);
+        let sites = self.get::<Site>(request).await?.items;
         Ok(sites
             .into_par_iter()
             .map(|site| {
@@ -159,10 +502,55 @@ impl Api {
             .collect())
     }
 
-    fn get_default_se_opts(&self) -> HashMap<&str, &str> {
+    /// Send a request, waiting out any `backoff` from previous responses, and
+    /// parse the response wrapper. Records the new `backoff` and quota, and
+    /// converts API errors to `Error`s.
+    async fn get<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<ResponseWrapper<T>> {
+        let backoff_until = *self.backoff_until.lock().unwrap();
+        if let Some(until) = backoff_until {
+            if until > Instant::now() {
+                log::debug!("Backing off until {until:?}");
+            }
+            time::sleep_until(until).await;
+        }
+        let rsp = http::send(request, self.retries).await?;
+        let status_code = rsp.status();
+        let url = rsp.url().clone();
+        let body = rsp.text().await?;
+        log::debug!("Stack exchange returned status {status_code} for {url} and body {body}");
+        let wrapper = serde_json::from_str::<ResponseWrapper<T>>(&body)?;
+        if let Some(backoff) = wrapper.backoff {
+            let until = Instant::now() + Duration::from_secs(backoff);
+            let mut backoff_until = self.backoff_until.lock().unwrap();
+            *backoff_until = (*backoff_until).max(Some(until));
+        }
+        if let Some(quota) = wrapper.quota_remaining {
+            *self.quota_remaining.lock().unwrap() = Some(quota);
+        }
+        wrapper.into_result()
+    }
+
+    /// Get the post filter, which is `SE_FILTER` extended with metadata
+    /// fields. Metadata is nice to have, so falls back to `SE_FILTER` on failure.
+    async fn get_filter(&self) -> &str {
+        self.filter
+            .get_or_init(|| async {
+                self.create_filter(SE_FILTER, SE_FILTER_INCLUDE)
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::warn!("Failed to create metadata filter: {e}");
+                        String::from(SE_FILTER)
+                    })
+            })
+            .await
+    }
+
+    async fn get_default_se_opts(&self) -> HashMap<&str, &str> {
         let mut params = HashMap::new();
-        params.insert("filter", SE_FILTER);
-        params.insert("page", "1");
+        params.insert("filter", self.get_filter().await);
         if let Some(key) = &self.api_key {
             params.insert("key", key);
         }
@@ -196,25 +584,186 @@ impl Api {
     }
 }
 
-/// Creates stackexchange API url given endpoint
-// TODO lazy static this url parse
-fn stackexchange_url(path: &str) -> Url {
-    let mut url = Url::parse(SE_API_URL).unwrap();
-    url.path_segments_mut()
-        .unwrap()
-        .push(SE_API_VERSION)
-        .extend(path.split('/'));
-    url
+/// The parameter holding the query, depending on whether only titles match
+fn query_param(filters: &SearchFilters) -> &'static str {
+    if filters.title_only {
+        "title"
+    } else {
+        "q"
+    }
+}
+
+/// Parameters for the sort and date range of all search endpoints. A minimum
+/// only applies to the sorted field, so the minimum score only to votes.
+fn filter_params(filters: &SearchFilters) -> Vec<(&'static str, String)> {
+    let mut params = vec![("sort", filters.sort.to_string())];
+    if let Some(date) = filters.from_date {
+        params.push(("fromdate", date.start().to_string()));
+    }
+    if let Some(date) = filters.to_date {
+        params.push(("todate", date.end().to_string()));
+    }
+    if let (SearchSort::Votes, Some(score)) = (filters.sort, filters.min_score) {
+        params.push(("min", score.to_string()));
+    }
+    params
+}
+
+/// Parameters for accepted answers and closed questions, supported by the
+/// /search endpoints
+fn state_params(filters: &SearchFilters) -> Vec<(&'static str, String)> {
+    let mut params = vec![];
+    if filters.accepted {
+        params.push(("accepted", String::from("True")));
+    }
+    if filters.exclude_closed {
+        params.push(("closed", String::from("False")));
+    }
+    params
+}
+
+/// The `tagged` parameter restricting a search to questions with all of the
+/// given tags, if any
+fn tagged(tags: &[String]) -> Vec<(&'static str, String)> {
+    if tags.is_empty() {
+        vec![]
+    } else {
+        vec![("tagged", tags.join(";"))]
+    }
+}
+
+impl<T> ResponseWrapper<T> {
+    /// Convert an API error response to an `Error`
+    fn into_result(self) -> Result<Self> {
+        let Some(id) = self.error_id else {
+            return Ok(self);
+        };
+        let name = self.error_name.unwrap_or_default();
+        let message = self.error_message.unwrap_or_default();
+        Err(match id {
+            SE_THROTTLE_VIOLATION => Error::Throttled(message),
+            _ => Error::StackExchangeApi { id, name, message },
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_filter_params() {
+        let filters = SearchFilters {
+            accepted: true,
+            min_score: Some(5),
+            to_date: Some("1970-01-01".parse().unwrap()),
+            title_only: true,
+            ..SearchFilters::default()
+        };
+        assert_eq!(query_param(&filters), "title");
+        // The minimum score only applies when sorting by votes
+        assert_eq!(
+            filter_params(&filters),
+            vec![
+                ("sort", String::from("relevance")),
+                ("todate", String::from("86399"))
+            ]
+        );
+        assert_eq!(
+            state_params(&filters),
+            vec![("accepted", String::from("True"))]
+        );
+
+        let filters = SearchFilters {
+            min_score: Some(5),
+            sort: SearchSort::Votes,
+            exclude_closed: true,
+            ..SearchFilters::default()
+        };
+        assert_eq!(query_param(&filters), "q");
+        assert_eq!(
+            filter_params(&filters),
+            vec![("sort", String::from("votes")), ("min", String::from("5"))]
+        );
+        assert_eq!(
+            state_params(&filters),
+            vec![("closed", String::from("False"))]
+        );
+    }
     #[test]
     fn test_stackexchange_url() {
+        let api = Api::new(&Config::default()).unwrap();
         assert_eq!(
-            stackexchange_url("some/endpoint").as_str(),
+            api.url("some/endpoint").as_str(),
             "https://api.stackexchange.com/2.2/some/endpoint"
-        )
+        );
+
+        let config = Config {
+            api_url: Some(String::from("http://localhost:8080/mirror/")),
+            api_version: Some(String::from("2.3")),
+            ..Config::default()
+        };
+        let api = Api::new(&config).unwrap();
+        assert_eq!(
+            api.url("some/endpoint").as_str(),
+            "http://localhost:8080/mirror/2.3/some/endpoint"
+        );
+
+        let config = Config {
+            api_url: Some(String::from("not a url")),
+            ..Config::default()
+        };
+        assert!(matches!(Api::new(&config), Err(Error::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn test_error_response() {
+        let json = r#"{
+            "error_id": 502,
+            "error_message": "too many requests from this IP, more requests available in 72 seconds",
+            "error_name": "throttle_violation"
+        }"#;
+        let rsp = serde_json::from_str::<ResponseWrapper<Question<String>>>(json).unwrap();
+        assert!(matches!(
+            rsp.into_result(),
+            Err(Error::Throttled(msg)) if msg.ends_with("72 seconds")
+        ));
+
+        let json = r#"{"error_id": 400, "error_message": "site is required", "error_name": "bad_parameter"}"#;
+        let rsp = serde_json::from_str::<ResponseWrapper<Question<String>>>(json).unwrap();
+        assert!(matches!(
+            rsp.into_result(),
+            Err(Error::StackExchangeApi { id: 400, name, .. }) if name == "bad_parameter"
+        ));
+
+        let json = r#"{"items": [], "quota_remaining": 12, "backoff": 10}"#;
+        let rsp = serde_json::from_str::<ResponseWrapper<Question<String>>>(json).unwrap();
+        assert_eq!(rsp.quota_remaining, Some(12));
+        assert_eq!(rsp.backoff, Some(10));
+        assert!(rsp.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_question_metadata_is_optional() {
+        let json = r#"{"items": [{
+            "question_id": 1, "score": 2, "title": "t", "body_markdown": "b",
+            "answers": [{"answer_id": 3, "score": 4, "body_markdown": "a", "is_accepted": true}]
+        }]}"#;
+        let q = &serde_json::from_str::<ResponseWrapper<Question<String>>>(json)
+            .unwrap()
+            .items[0];
+        assert!(q.tags.is_empty() && q.owner.is_none() && q.closed_reason.is_none());
+
+        let json = r#"{"items": [{
+            "question_id": 1, "score": 2, "title": "t", "body_markdown": "b",
+            "tags": ["vim"], "owner": {"display_name": "sam", "reputation": 10},
+            "creation_date": 1, "view_count": 5, "closed_reason": "Duplicate"
+        }]}"#;
+        let q = &serde_json::from_str::<ResponseWrapper<Question<String>>>(json)
+            .unwrap()
+            .items[0];
+        assert_eq!(q.tags, vec![String::from("vim")]);
+        assert_eq!(q.owner.as_ref().unwrap().reputation, Some(10));
+        assert_eq!(q.view_count, Some(5));
     }
 }
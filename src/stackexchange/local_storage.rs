@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fs;
+use std::iter::FromIterator;
 use std::ops::Deref;
 use std::path::Path;
 
@@ -8,6 +9,7 @@ use crate::error::{Error, Result};
 use crate::utils;
 
 use super::api::{Api, Site};
+use super::scraper::{post_url_to_id, PostLink};
 use super::Question;
 
 /// This structure allows interacting with locally cached StackExchange metadata.
@@ -30,32 +32,29 @@ impl LocalStorage {
         Ok(())
     }
 
-    async fn init_sites(filename: &Path, update: bool) -> Result<Vec<Site>> {
+    async fn init_sites(filename: &Path, update: bool, config: &Config) -> Result<Vec<Site>> {
         if !update {
             if let Some(sites) = Self::fetch_local_sites(filename)? {
                 return Ok(sites);
             }
         }
-        let sites = Api::new(None).sites().await?;
+        let sites = Api::new(config)?.sites().await?;
         Self::store_local_sites(filename, &sites)?;
         Ok(sites)
     }
 
-    pub async fn new(update: bool) -> Result<Self> {
+    pub async fn new(update: bool, config: &Config) -> Result<Self> {
         let project = Config::project_dir()?;
         let dir = project.cache_dir();
         fs::create_dir_all(dir)?;
         let sites_filename = dir.join("sites.json");
-        let sites = Self::init_sites(&sites_filename, update).await?;
+        let sites = Self::init_sites(&sites_filename, update, config).await?;
         Ok(LocalStorage { sites })
     }
 
     // TODO is this HM worth it? Probably only will ever have < 10 site codes to search...
     // maybe store this as Option<HM> on self if other methods use it...
-    pub async fn find_invalid_site<'a, 'b>(
-        &'b self,
-        site_codes: &'a [String],
-    ) -> Option<&'a String> {
+    pub async fn find_invalid_site<'a>(&self, site_codes: &'a [String]) -> Option<&'a String> {
         let hm: HashMap<&str, ()> = self
             .sites
             .iter()
@@ -64,9 +63,31 @@ impl LocalStorage {
         site_codes.iter().find(|s| !hm.contains_key(&s.as_str()))
     }
 
+    /// Find the site and post referred to by a question or answer url, or by
+    /// `site:id` where `id` is a question id.
+    pub fn find_post(&self, target: &str) -> Option<(String, PostLink)> {
+        if let Some((code, id)) = target.split_once(':') {
+            if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) {
+                return self
+                    .sites
+                    .iter()
+                    .find(|site| site.api_site_parameter == code)
+                    .map(|site| {
+                        (
+                            site.api_site_parameter.to_owned(),
+                            PostLink::Question(id.to_owned()),
+                        )
+                    });
+            }
+        }
+        self.sites.iter().find_map(|site| {
+            let link = post_url_to_id(&site.site_url, target)?;
+            Some((site.api_site_parameter.to_owned(), link))
+        })
+    }
+
     pub fn get_site_map(&self, site_codes: &[String]) -> SiteMap {
-        let inner = self
-            .sites
+        self.sites
             .iter()
             .filter_map(move |site| {
                 let _ = site_codes
@@ -74,8 +95,7 @@ impl LocalStorage {
                     .find(|&sc| *sc == site.api_site_parameter)?;
                 Some((site.api_site_parameter.to_owned(), site.site_url.to_owned()))
             })
-            .collect();
-        SiteMap { inner }
+            .collect()
     }
 }
 
@@ -95,6 +115,14 @@ impl Deref for SiteMap {
     }
 }
 
+impl FromIterator<(String, String)> for SiteMap {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        SiteMap {
+            inner: iter.into_iter().collect(),
+        }
+    }
+}
+
 impl SiteMap {
     /// Get SE answer url. Panics if site was not set on the question, or
     /// site code not found in the map.
@@ -1,12 +1,14 @@
 mod api;
+mod http;
 mod local_storage;
 mod search;
 // Exposed for benchmarking
 pub mod scraper;
 
-pub use api::{Answer, Id, Question};
-pub use local_storage::{LocalStorage, SiteMap};
-pub use search::Search;
+pub use api::{Answer, Comment, Id, PostKey, Question, User};
+pub use local_storage::LocalStorage;
+pub use scraper::PostLink;
+pub use search::{Search, SiteBatch};
 
 /// Mock user agent
 const USER_AGENT: &str =
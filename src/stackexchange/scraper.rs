@@ -1,45 +1,129 @@
-use percent_encoding::percent_decode_str;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::Url;
 use scraper::html::Html;
 use scraper::selector::Selector;
+use serde::Deserialize;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
+use crate::config::CustomEngine;
 use crate::error::{Error, Result};
 
-/// DuckDuckGo URL
+use super::http;
+
+/// Default search URLs
 const DUCKDUCKGO_URL: &str = "https://duckduckgo.com";
 const GOOGLE_URL: &str = "https://google.com/search";
+const BING_URL: &str = "https://www.bing.com/search";
+const BRAVE_URL: &str = "https://search.brave.com/search";
+const STARTPAGE_URL: &str = "https://www.startpage.com/sp/search";
+
+/// Number of results on a single page of search results
+const DUCKDUCKGO_PAGESIZE: u16 = 30;
+const GOOGLE_PAGESIZE: u16 = 10;
+const BING_PAGESIZE: u16 = 10;
 
-// Is question_id unique across all sites? If not, then this edge case is
-// unaccounted for when sorting.
-//
-// If this is ever an issue, it wouldn't be too hard to account for this; just
-// keep track of site in the `ordering` field and also return site from the
-// spawned per-site tasks.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct ScrapedData {
     /// Mapping of site code to question ids
     pub question_ids: HashMap<String, Vec<String>>,
-    /// Mapping of question_id to its ordinal place in search results
-    pub ordering: HashMap<String, usize>,
+    /// Mapping of (site code, question_id) to its ordinal place in search
+    /// results; question ids are only unique within a site
+    pub ordering: HashMap<(String, String), usize>,
+    /// Mapping of (site code, question_id) to the answer that a result linked to
+    pub answer_ids: HashMap<(String, String), String>,
+    /// Mapping of (site code, answer_id) to its ordinal place in search
+    /// results, for answer links that don't include their question id
+    pub answer_links: HashMap<(String, String), usize>,
+}
+
+impl ScrapedData {
+    /// Add a question at an ordinal place in the search results. If it is
+    /// already present, it keeps the higher place, and this returns false.
+    pub fn insert_question(&mut self, site: &str, id: String, place: usize) -> bool {
+        match self.ordering.entry((site.to_owned(), id.clone())) {
+            Entry::Occupied(mut o) => {
+                *o.get_mut() = place.min(*o.get());
+                false
+            }
+            Entry::Vacant(o) => {
+                o.insert(place);
+                self.question_ids
+                    .entry(site.to_owned())
+                    .or_default()
+                    .push(id);
+                true
+            }
+        }
+    }
+
+    /// Keep only the `limit` highest placed questions, e.g. once answer links
+    /// have been resolved to more questions than asked for
+    pub fn truncate(&mut self, limit: usize) {
+        let mut places = self.ordering.values().copied().collect::<Vec<_>>();
+        if places.len() <= limit {
+            return;
+        }
+        places.sort_unstable();
+        let cutoff = places[limit];
+        self.ordering.retain(|_, place| *place < cutoff);
+        let ordering = &self.ordering;
+        for (site, ids) in self.question_ids.iter_mut() {
+            ids.retain(|id| ordering.contains_key(&(site.clone(), id.clone())));
+        }
+        self.question_ids.retain(|_, ids| !ids.is_empty());
+        self.answer_ids.retain(|key, _| ordering.contains_key(key));
+    }
+
+    /// Record that a result linked to this answer of the question, unless
+    /// another of its answers was linked to first
+    pub fn insert_answer(&mut self, site: &str, question_id: String, answer_id: String) {
+        self.answer_ids
+            .entry((site.to_owned(), question_id))
+            .or_insert(answer_id);
+    }
 }
 
 // TODO add this type system limitation to blog post
 pub trait Scraper {
+    /// Name of the search engine, for error messages
+    fn name(&self) -> &str;
+
     /// Parse data from search results html
     fn parse(&self, html: &str, sites: &HashMap<String, String>, limit: u16)
         -> Result<ScrapedData>;
 
-    /// Get the url to search query restricted to sites
-    fn get_url<'a, I>(&self, query: &str, sites: I) -> Url
+    /// Get the url to search query restricted to sites, at the given (1-indexed) results page
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
     where
         I: IntoIterator<Item = &'a String>;
 }
 
-pub struct DuckDuckGo;
+pub struct DuckDuckGo {
+    url: Url,
+}
+
+impl DuckDuckGo {
+    /// DuckDuckGo scraper searching at `url`, or the default DuckDuckGo URL
+    pub fn new(url: Option<&str>) -> Result<Self> {
+        let url = http::base_url(url.unwrap_or(DUCKDUCKGO_URL))?;
+        Ok(DuckDuckGo { url })
+    }
+}
+
+impl Default for DuckDuckGo {
+    fn default() -> Self {
+        Self::new(None).unwrap()
+    }
+}
 
 impl Scraper for DuckDuckGo {
+    fn name(&self) -> &str {
+        "DuckDuckGo"
+    }
+
     /// Parse (site, question_id) pairs out of duckduckgo search results html
     fn parse(
         &self,
@@ -48,7 +132,8 @@ impl Scraper for DuckDuckGo {
         limit: u16,
     ) -> Result<ScrapedData> {
         let anchors = Selector::parse("a.result__a").unwrap();
-        parse_with_selector(anchors, html, sites, limit).and_then(|sd| {
+        let html = Html::parse_document(html);
+        parse_with_selector(anchors, &html, sites, limit).and_then(|sd| {
             // DDG seems to never have empty results, so assume this is blocked
             if sd.question_ids.is_empty() {
                 Err(Error::Scraping(String::from(
@@ -62,22 +147,44 @@ impl Scraper for DuckDuckGo {
 
     /// Creates duckduckgo search url given sites and query
     /// See https://duckduckgo.com/params for more info
-    fn get_url<'a, I>(&self, query: &str, sites: I) -> Url
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
     where
         I: IntoIterator<Item = &'a String>,
     {
         let q = make_query_arg(query, sites);
-        Url::parse_with_params(
-            DUCKDUCKGO_URL,
-            &[("q", q.as_str()), ("kz", "-1"), ("kh", "-1")],
-        )
-        .unwrap()
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .extend_pairs(&[("q", q.as_str()), ("kz", "-1"), ("kh", "-1")]);
+        if let Some(offset) = page_offset(page, DUCKDUCKGO_PAGESIZE) {
+            url.query_pairs_mut().append_pair("s", &offset);
+        }
+        url
+    }
+}
+
+pub struct Google {
+    url: Url,
+}
+
+impl Google {
+    /// Google scraper searching at `url`, or the default Google URL
+    pub fn new(url: Option<&str>) -> Result<Self> {
+        let url = http::base_url(url.unwrap_or(GOOGLE_URL))?;
+        Ok(Google { url })
     }
 }
 
-pub struct Google;
+impl Default for Google {
+    fn default() -> Self {
+        Self::new(None).unwrap()
+    }
+}
 
 impl Scraper for Google {
+    fn name(&self) -> &str {
+        "Google"
+    }
+
     /// Parse SE data out of google search results html
     fn parse(
         &self,
@@ -86,117 +193,558 @@ impl Scraper for Google {
         limit: u16,
     ) -> Result<ScrapedData> {
         let anchors = Selector::parse("a").unwrap();
-        parse_with_selector(anchors, html, sites, limit)
+        let html = Html::parse_document(html);
+        // Outside the US, Google may first ask for cookie consent
+        let consent = Selector::parse(r#"form[action*="consent.google"]"#).unwrap();
+        if html.select(&consent).next().is_some() {
+            return Err(Error::ConsentRequired(self.name().to_string()));
+        }
+        // The "unusual traffic" page at /sorry/
+        let sorry = Selector::parse(r#"form[action*="/sorry/"]"#).unwrap();
+        if has_captcha(&html) || html.select(&sorry).next().is_some() {
+            return Err(Error::Captcha(self.name().to_string()));
+        }
+        parse_with_selector(anchors, &html, sites, limit)
     }
 
-    /// Creates duckduckgo search url given sites and query
-    /// See https://duckduckgo.com/params for more info
-    fn get_url<'a, I>(&self, query: &str, sites: I) -> Url
+    /// Creates google search url given sites and query
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let q = make_query_arg(query, sites);
+        let mut url = self.url.clone();
+        url.query_pairs_mut().append_pair("q", &q);
+        if let Some(offset) = page_offset(page, GOOGLE_PAGESIZE) {
+            url.query_pairs_mut().append_pair("start", &offset);
+        }
+        url
+    }
+}
+
+pub struct Bing {
+    url: Url,
+}
+
+impl Bing {
+    /// Bing scraper searching at `url`, or the default Bing URL
+    pub fn new(url: Option<&str>) -> Result<Self> {
+        let url = http::base_url(url.unwrap_or(BING_URL))?;
+        Ok(Bing { url })
+    }
+}
+
+impl Default for Bing {
+    fn default() -> Self {
+        Self::new(None).unwrap()
+    }
+}
+
+impl Scraper for Bing {
+    fn name(&self) -> &str {
+        "Bing"
+    }
+
+    /// Parse SE data out of bing search results html
+    fn parse(
+        &self,
+        html: &str,
+        sites: &HashMap<String, String>,
+        limit: u16,
+    ) -> Result<ScrapedData> {
+        let html = Html::parse_document(html);
+        // Even a search without results has a results list
+        let results = Selector::parse("#b_results").unwrap();
+        if has_captcha(&html) || html.select(&results).next().is_none() {
+            return Err(Error::Captcha(self.name().to_string()));
+        }
+        let anchors = Selector::parse("#b_results li.b_algo h2 a").unwrap();
+        let hrefs = html
+            .select(&anchors)
+            .filter_map(|a| a.value().attr("href"))
+            .map(bing_redirect_target);
+        parse_links(hrefs, sites, limit)
+    }
+
+    /// Creates bing search url given sites and query
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
     where
         I: IntoIterator<Item = &'a String>,
     {
         let q = make_query_arg(query, sites);
-        Url::parse_with_params(GOOGLE_URL, &[("q", q.as_str())]).unwrap()
+        let mut url = self.url.clone();
+        url.query_pairs_mut().append_pair("q", &q);
+        // Bing's offset is 1-indexed
+        if page > 1 {
+            let first = (page as u32 - 1) * BING_PAGESIZE as u32 + 1;
+            url.query_pairs_mut()
+                .append_pair("first", &first.to_string());
+        }
+        url
+    }
+}
+
+/// Bing may link results through a click tracker, of the form
+/// `/ck/a?...&u=a1{base64 url}`; resolve these to the actual result url
+fn bing_redirect_target(href: &str) -> String {
+    Url::parse(href)
+        .ok()
+        .filter(|url| url.path() == "/ck/a")
+        .and_then(|url| {
+            let (_, target) = url.query_pairs().find(|(k, _)| k == "u")?;
+            let decoded = URL_SAFE_NO_PAD.decode(target.strip_prefix("a1")?).ok()?;
+            String::from_utf8(decoded).ok()
+        })
+        .unwrap_or_else(|| href.to_string())
+}
+
+pub struct Brave {
+    url: Url,
+}
+
+impl Brave {
+    /// Brave scraper searching at `url`, or the default Brave Search URL
+    pub fn new(url: Option<&str>) -> Result<Self> {
+        let url = http::base_url(url.unwrap_or(BRAVE_URL))?;
+        Ok(Brave { url })
+    }
+}
+
+impl Default for Brave {
+    fn default() -> Self {
+        Self::new(None).unwrap()
     }
 }
 
+impl Scraper for Brave {
+    fn name(&self) -> &str {
+        "Brave Search"
+    }
+
+    /// Parse SE data out of brave search results html
+    fn parse(
+        &self,
+        html: &str,
+        sites: &HashMap<String, String>,
+        limit: u16,
+    ) -> Result<ScrapedData> {
+        let html = Html::parse_document(html);
+        if has_captcha(&html) {
+            return Err(Error::Captcha(self.name().to_string()));
+        }
+        let anchors = Selector::parse(r#"#results .snippet[data-type="web"] a"#).unwrap();
+        parse_with_selector(anchors, &html, sites, limit)
+    }
+
+    /// Creates brave search url given sites and query
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let q = make_query_arg(query, sites);
+        let mut url = self.url.clone();
+        url.query_pairs_mut().append_pair("q", &q);
+        // Brave's offset counts pages rather than results
+        if page > 1 {
+            url.query_pairs_mut()
+                .append_pair("offset", &(page - 1).to_string());
+        }
+        url
+    }
+}
+
+pub struct Startpage {
+    url: Url,
+}
+
+impl Startpage {
+    /// Startpage scraper searching at `url`, or the default Startpage URL
+    pub fn new(url: Option<&str>) -> Result<Self> {
+        let url = http::base_url(url.unwrap_or(STARTPAGE_URL))?;
+        Ok(Startpage { url })
+    }
+}
+
+impl Default for Startpage {
+    fn default() -> Self {
+        Self::new(None).unwrap()
+    }
+}
+
+impl Scraper for Startpage {
+    fn name(&self) -> &str {
+        "Startpage"
+    }
+
+    /// Parse SE data out of startpage search results html
+    fn parse(
+        &self,
+        html: &str,
+        sites: &HashMap<String, String>,
+        limit: u16,
+    ) -> Result<ScrapedData> {
+        let html = Html::parse_document(html);
+        if has_captcha(&html) {
+            return Err(Error::Captcha(self.name().to_string()));
+        }
+        let anchors = Selector::parse("a.result-link").unwrap();
+        parse_with_selector(anchors, &html, sites, limit)
+    }
+
+    /// Creates startpage search url given sites and query
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let q = make_query_arg(query, sites);
+        let mut url = self.url.clone();
+        url.query_pairs_mut().append_pair("query", &q);
+        if page > 1 {
+            url.query_pairs_mut().append_pair("page", &page.to_string());
+        }
+        url
+    }
+}
+
+pub struct Searxng {
+    url: Url,
+}
+
+impl Searxng {
+    /// SearXNG client for the instance at `url`; there is no public default
+    pub fn new(url: Option<&str>) -> Result<Self> {
+        let url = url.ok_or_else(|| Error::MissingConfig(String::from("searxng_url")))?;
+        let mut url = http::base_url(url)?;
+        url.path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .push("search");
+        Ok(Searxng { url })
+    }
+}
+
+#[derive(Deserialize)]
+struct SearxngResponse {
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Deserialize)]
+struct SearxngResult {
+    url: String,
+}
+
+impl Scraper for Searxng {
+    fn name(&self) -> &str {
+        "SearXNG"
+    }
+
+    /// Parse SE data out of a SearXNG JSON response
+    fn parse(
+        &self,
+        json: &str,
+        sites: &HashMap<String, String>,
+        limit: u16,
+    ) -> Result<ScrapedData> {
+        // Instances serve HTML or an error page unless JSON is enabled
+        let response: SearxngResponse = serde_json::from_str(json).map_err(|_| {
+            Error::Scraping(String::from(
+                "SearXNG returned an invalid response; is the JSON format enabled on the instance?",
+            ))
+        })?;
+        parse_links(response.results.iter().map(|r| &r.url), sites, limit)
+    }
+
+    /// Creates SearXNG search url given sites and query
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let q = make_query_arg(query, sites);
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .extend_pairs(&[("q", q.as_str()), ("format", "json")]);
+        if page > 1 {
+            url.query_pairs_mut()
+                .append_pair("pageno", &page.to_string());
+        }
+        url
+    }
+}
+
+/// A search engine defined in the config file
+pub struct Custom {
+    name: String,
+    url: String,
+    /// The first page of results, which relative links are resolved against
+    base: Url,
+    anchors: Selector,
+    href_attribute: String,
+    redirect_param: Option<String>,
+    blocked: Option<Selector>,
+}
+
+impl Custom {
+    /// Scraper for an engine definition, checking its URL template and selectors
+    pub fn new(engine: &CustomEngine) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidEngine(engine.name.clone(), reason);
+        let selector = |s: &str| {
+            Selector::parse(s).map_err(|e| invalid(format!("invalid selector `{s}`: {e}")))
+        };
+        if !engine.url.contains("{query}") {
+            return Err(invalid(String::from("`url` has no `{query}` placeholder")));
+        }
+        let base = expand_url(&engine.url, "", "", 1)?;
+        Ok(Custom {
+            name: engine.name.clone(),
+            url: engine.url.clone(),
+            base,
+            anchors: selector(&engine.selector)?,
+            href_attribute: engine
+                .href_attribute
+                .clone()
+                .unwrap_or_else(|| String::from("href")),
+            redirect_param: engine.redirect_param.clone(),
+            blocked: engine.blocked.as_deref().map(selector).transpose()?,
+        })
+    }
+
+    /// Whether the URL template can request pages past the first
+    pub fn paginates(&self) -> bool {
+        self.url.contains("{page}")
+    }
+
+    /// Result URL of a link, following the redirect parameter if configured
+    fn link_target(&self, href: &str) -> String {
+        let redirect = self.redirect_param.as_ref().and_then(|param| {
+            // Redirect links are usually relative to the search page
+            let url = self.base.join(href).ok()?;
+            let (_, target) = url.query_pairs().find(|(k, _)| k == param)?;
+            Some(target.into_owned())
+        });
+        redirect.unwrap_or_else(|| href.to_string())
+    }
+}
+
+impl Scraper for Custom {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parse SE data out of search results html per the engine definition
+    fn parse(
+        &self,
+        html: &str,
+        sites: &HashMap<String, String>,
+        limit: u16,
+    ) -> Result<ScrapedData> {
+        let html = Html::parse_document(html);
+        if let Some(blocked) = &self.blocked {
+            if html.select(blocked).next().is_some() {
+                return Err(Error::Scraping(format!(
+                    "{} blocked this request",
+                    self.name
+                )));
+            }
+        }
+        let hrefs = html
+            .select(&self.anchors)
+            .filter_map(|a| a.value().attr(&self.href_attribute))
+            .map(|href| self.link_target(href));
+        parse_links(hrefs, sites, limit)
+    }
+
+    /// Expands the URL template given sites and query
+    fn get_url<'a, I>(&self, query: &str, sites: I, page: u16) -> Url
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        expand_url(
+            &self.url,
+            &query_terms(query),
+            &site_restriction(sites),
+            page,
+        )
+        .expect("bug: url template is validated on construction")
+    }
+}
+
+/// Fill in the placeholders of a search URL template
+fn expand_url(template: &str, query: &str, sites: &str, page: u16) -> Result<Url> {
+    let encode = |s: &str| utf8_percent_encode(s, NON_ALPHANUMERIC).to_string();
+    let url = template
+        .replace("{query}", &encode(query))
+        .replace("{sites}", &encode(sites))
+        .replace("{page}", &page.to_string());
+    Url::parse(&url).map_err(|_| Error::InvalidUrl(template.to_string()))
+}
+
+/// Whether the page is a captcha challenge rather than search results
+fn has_captcha(html: &Html) -> bool {
+    let captcha =
+        Selector::parse(r#"form[action*="captcha"], [id*="captcha"], iframe[src*="captcha"]"#)
+            .unwrap();
+    html.select(&captcha).next().is_some()
+}
+
+/// Result offset of the given (1-indexed) page, if past the first page
+fn page_offset(page: u16, pagesize: u16) -> Option<String> {
+    (page > 1).then(|| ((page as u32 - 1) * pagesize as u32).to_string())
+}
+
 fn make_query_arg<'a, I>(query: &str, sites: I) -> String
 where
     I: IntoIterator<Item = &'a String>,
 {
-    let mut q = String::new();
-    //  Restrict to sites
-    q.push('(');
-    q.push_str(
-        sites
-            .into_iter()
-            .map(|site| String::from("site:") + site)
-            .collect::<Vec<_>>()
-            .join(" OR ")
-            .as_str(),
-    );
-    q.push_str(") ");
-    //  Search terms
-    q.push_str(
-        query
-            .trim_end_matches('?')
-            .split_whitespace()
-            .collect::<Vec<_>>()
-            .join(" ")
-            .as_str(),
-    );
-    q
+    format!("{} {}", site_restriction(sites), query_terms(query))
+}
+
+/// Restrict a search to sites, e.g. `(site:a.com OR site:b.com)`
+fn site_restriction<'a, I>(sites: I) -> String
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let sites = sites
+        .into_iter()
+        .map(|site| String::from("site:") + site)
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    format!("({sites})")
+}
+
+/// Normalized search terms
+fn query_terms(query: &str) -> String {
+    query
+        .trim_end_matches('?')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 fn parse_with_selector(
     anchors: Selector,
-    html: &str,
+    html: &Html,
     sites: &HashMap<String, String>,
     limit: u16,
 ) -> Result<ScrapedData> {
-    let fragment = Html::parse_document(html);
-    let mut question_ids: HashMap<String, Vec<String>> = HashMap::new();
-    let mut ordering: HashMap<String, usize> = HashMap::new();
+    let hrefs = html
+        .select(&anchors)
+        .filter_map(|anchor| anchor.value().attr("href"));
+    parse_links(hrefs, sites, limit)
+}
+
+/// Collect the questions and answers linked to, in order, up to `limit`
+/// questions. Answer links without a question id don't count towards the
+/// limit, as their questions may turn out to be duplicates.
+fn parse_links<I>(hrefs: I, sites: &HashMap<String, String>, limit: u16) -> Result<ScrapedData>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut data = ScrapedData::default();
     let mut count = 0;
-    for anchor in fragment.select(&anchors) {
-        if let Some(url) = anchor
-            .value()
-            .attr("href")
-            .map(|href| percent_decode_str(href).decode_utf8_lossy())
-        {
-            sites.iter().find_map(|(site_code, site_url)| {
-                let id = question_url_to_id(site_url, &url)?;
-                ordering.insert(id.to_owned(), count);
-                match question_ids.entry(site_code.to_owned()) {
-                    Entry::Occupied(mut o) => o.get_mut().push(id),
-                    Entry::Vacant(o) => {
-                        o.insert(vec![id]);
-                    }
+    for href in hrefs {
+        let url = percent_decode_str(href.as_ref()).decode_utf8_lossy();
+        let Some((site, link)) = sites
+            .iter()
+            .find_map(|(site_code, site_url)| Some((site_code, post_url_to_id(site_url, &url)?)))
+        else {
+            continue;
+        };
+        // Results may link the same post more than once
+        let new = match link {
+            PostLink::Question(id) => data.insert_question(site, id, count),
+            PostLink::Answer {
+                question_id: Some(question_id),
+                answer_id,
+            } => {
+                data.insert_answer(site, question_id.clone(), answer_id);
+                data.insert_question(site, question_id, count)
+            }
+            PostLink::Answer {
+                question_id: None,
+                answer_id,
+            } => match data.answer_links.entry((site.to_owned(), answer_id)) {
+                Entry::Occupied(_) => false,
+                Entry::Vacant(o) => {
+                    o.insert(count);
+                    true
                 }
-                count += 1;
-                Some(())
-            });
+            },
+        };
+        if new {
+            count += 1;
         }
-        if count >= limit as usize {
+        if data.ordering.len() >= limit as usize {
             break;
         }
     }
-    Ok(ScrapedData {
-        question_ids,
-        ordering,
-    })
+    Ok(data)
 }
 
+/// A StackExchange post linked to by a url
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostLink {
+    Question(String),
+    /// Answer links of the form `/a/{id}` do not contain the question id
+    Answer {
+        question_id: Option<String>,
+        answer_id: String,
+    },
+}
+
+/// Parse the linked post out of a url, if it belongs to the site. Understands
+/// `/questions/{id}`, `/q/{id}`, `/a/{id}`, and `/questions/{id}/{slug}/{answer_id}`.
 // TODO use str_prefix once its stable
-fn question_url_to_id(site_url: &str, input: &str) -> Option<String> {
-    ["/questions/", "/q/"].iter().find_map(|segment| {
+pub fn post_url_to_id(site_url: &str, input: &str) -> Option<PostLink> {
+    ["/questions/", "/q/", "/a/"].iter().find_map(|segment| {
         let fragment = site_url.trim_end_matches('/').to_owned() + segment;
         let mut ix = input.find(&fragment)?;
         if ix > 0 && input.chars().nth(ix - 1) == Some('.') {
             return None;
         }
         ix += fragment.len();
-        let input = &input[ix..];
-        let id = if let Some(end) = input.find('/') {
-            input[0..end].to_string()
-        } else {
-            input[0..].to_string()
-        };
-        if id.chars().all(|c| c.is_ascii_digit()) {
-            Some(id)
-        } else {
-            None
+        let mut parts = input[ix..].split('/');
+        let id = parts.next()?.to_string();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        if *segment == "/a/" {
+            return Some(PostLink::Answer {
+                question_id: None,
+                answer_id: id,
+            });
         }
+        // Skip the slug, then look for an answer id, e.g. `123#123`
+        let answer_id = parts
+            .nth(1)
+            .map(|s| {
+                s.chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect::<String>()
+            })
+            .filter(|s| !s.is_empty());
+        Some(match answer_id {
+            Some(answer_id) => PostLink::Answer {
+                question_id: Some(id),
+                answer_id,
+            },
+            None => PostLink::Question(id),
+        })
     })
 }
 
-// TODO  Get blocked google request html
-// TODO Get google no results html
-// note: this may only be possible at search.rs level (with non-200 code)
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The question a url links to, including answer links with a question id
+    fn question_url_to_id(site_url: &str, input: &str) -> Option<String> {
+        match post_url_to_id(site_url, input)? {
+            PostLink::Question(id) => Some(id),
+            PostLink::Answer { question_id, .. } => question_id,
+        }
+    }
+
     #[test]
     fn test_duckduckgo_url() {
         let q = "how do I exit vim?";
@@ -205,15 +753,42 @@ mod tests {
             String::from("unix.stackexchange.com"),
         ];
         assert_eq!(
-            DuckDuckGo.get_url(q, &sites).as_str(),
+            DuckDuckGo::default().get_url(q, &sites, 1).as_str(),
             String::from(
                 "https://duckduckgo.com/\
                 ?q=%28site%3Astackoverflow.com+OR+site%3Aunix.stackexchange.com%29\
                 +how+do+I+exit+vim&kz=-1&kh=-1"
             )
+        );
+        assert_eq!(
+            DuckDuckGo::default().get_url(q, &sites, 3).as_str(),
+            String::from(
+                "https://duckduckgo.com/\
+                ?q=%28site%3Astackoverflow.com+OR+site%3Aunix.stackexchange.com%29\
+                +how+do+I+exit+vim&kz=-1&kh=-1&s=60"
+            )
         )
     }
 
+    #[test]
+    fn test_google_url() {
+        let q = "how do I exit vim?";
+        let sites = vec![String::from("stackoverflow.com")];
+        assert_eq!(
+            Google::default().get_url(q, &sites, 1).as_str(),
+            "https://google.com/search?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim"
+        );
+        assert_eq!(
+            Google::default().get_url(q, &sites, 2).as_str(),
+            "https://google.com/search?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim&start=10"
+        );
+        let google = Google::new(Some("http://localhost:8080/search?hl=en")).unwrap();
+        assert_eq!(
+            google.get_url(q, &sites, 1).as_str(),
+            "http://localhost:8080/search?hl=en&q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim"
+        );
+    }
+
     #[test]
     fn test_duckduckgo_parser() {
         let html = include_str!("../../test/duckduckgo/exit-vim.html");
@@ -237,13 +812,18 @@ mod tests {
                 )
             })
             .collect(),
-            ordering: vec![("11828270", 0), ("9171356", 2), ("24406", 1)]
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
-                .collect(),
+            ordering: vec![
+                ("stackoverflow", "11828270", 0),
+                ("stackoverflow", "9171356", 2),
+                ("askubuntu", "24406", 1),
+            ]
+            .into_iter()
+            .map(|(s, id, v)| ((s.to_string(), id.to_string()), v))
+            .collect(),
+            ..Default::default()
         };
         assert_eq!(
-            DuckDuckGo.parse(html, &sites, 3).unwrap(),
+            DuckDuckGo::default().parse(html, &sites, 3).unwrap(),
             expected_scraped_data
         );
     }
@@ -271,13 +851,21 @@ mod tests {
                 )
             })
             .collect(),
-            ordering: vec![("11828270", 0), ("25919461", 1), ("24406", 2)]
+            ordering: vec![
+                ("stackoverflow", "11828270", 0),
+                ("stackoverflow", "25919461", 2),
+                ("askubuntu", "24406", 3),
+            ]
+            .into_iter()
+            .map(|(s, id, v)| ((s.to_string(), id.to_string()), v))
+            .collect(),
+            answer_links: vec![((String::from("stackoverflow"), String::from("11828573")), 1)]
                 .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
                 .collect(),
+            ..Default::default()
         };
         assert_eq!(
-            Google.parse(html, &sites, 3).unwrap(),
+            Google::default().parse(html, &sites, 3).unwrap(),
             expected_scraped_data
         );
     }
@@ -302,19 +890,359 @@ mod tests {
             .into_iter()
             .collect(),
             ordering: vec![
-                (String::from("3940128"), 0),
-                (String::from("4647368"), 1),
-                (String::from("12336105"), 2),
+                ((String::from("stackoverflow"), String::from("3940128")), 0),
+                ((String::from("stackoverflow"), String::from("4647368")), 2),
+                ((String::from("stackoverflow"), String::from("12336105")), 3),
             ]
             .into_iter()
             .collect(),
+            answer_links: vec![((String::from("stackoverflow"), String::from("3940144")), 1)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
         };
         assert_eq!(
-            Google.parse(html, &sites, 3).unwrap(),
+            Google::default().parse(html, &sites, 3).unwrap(),
             expected_scraped_data
         );
     }
 
+    #[test]
+    fn test_colliding_ids_parser() {
+        let html = include_str!("../../test/google/colliding-ids.html");
+        let sites = vec![
+            ("stackoverflow", "stackoverflow.com"),
+            ("superuser", "superuser.com"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<HashMap<String, String>>();
+        let scraped = Google::default().parse(html, &sites, 3).unwrap();
+        // The same id on different sites is ranked separately
+        let rank = |site: &str, id: &str| scraped.ordering[&(site.to_string(), id.to_string())];
+        assert_eq!(rank("superuser", "11828270"), 0);
+        assert_eq!(rank("stackoverflow", "11828270"), 1);
+        assert_eq!(rank("stackoverflow", "25919461"), 2);
+    }
+
+    /// Sites searched in the `exit-vim.html` fixtures
+    fn exit_vim_sites() -> HashMap<String, String> {
+        vec![
+            ("stackoverflow", "stackoverflow.com"),
+            ("askubuntu", "askubuntu.com"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    /// Scraped data for a ranked list of (site, question id) results
+    fn scraped(ranking: &[(&str, &str)]) -> ScrapedData {
+        let mut question_ids: HashMap<String, Vec<String>> = HashMap::new();
+        let mut ordering = HashMap::new();
+        for (ix, (site, id)) in ranking.iter().enumerate() {
+            question_ids
+                .entry(site.to_string())
+                .or_default()
+                .push(id.to_string());
+            ordering.insert((site.to_string(), id.to_string()), ix);
+        }
+        ScrapedData {
+            question_ids,
+            ordering,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bing_url() {
+        let q = "how do I exit vim?";
+        let sites = vec![String::from("stackoverflow.com")];
+        assert_eq!(
+            Bing::default().get_url(q, &sites, 1).as_str(),
+            "https://www.bing.com/search?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim"
+        );
+        assert_eq!(
+            Bing::default().get_url(q, &sites, 3).as_str(),
+            "https://www.bing.com/search?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim&first=21"
+        );
+    }
+
+    #[test]
+    fn test_brave_url() {
+        let q = "how do I exit vim?";
+        let sites = vec![String::from("stackoverflow.com")];
+        assert_eq!(
+            Brave::default().get_url(q, &sites, 1).as_str(),
+            "https://search.brave.com/search?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim"
+        );
+        assert_eq!(
+            Brave::default().get_url(q, &sites, 3).as_str(),
+            "https://search.brave.com/search?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim&offset=2"
+        );
+    }
+
+    #[test]
+    fn test_startpage_url() {
+        let q = "how do I exit vim?";
+        let sites = vec![String::from("stackoverflow.com")];
+        assert_eq!(
+            Startpage::default().get_url(q, &sites, 1).as_str(),
+            "https://www.startpage.com/sp/search\
+            ?query=%28site%3Astackoverflow.com%29+how+do+I+exit+vim"
+        );
+        assert_eq!(
+            Startpage::default().get_url(q, &sites, 2).as_str(),
+            "https://www.startpage.com/sp/search\
+            ?query=%28site%3Astackoverflow.com%29+how+do+I+exit+vim&page=2"
+        );
+    }
+
+    #[test]
+    fn test_bing_parser() {
+        // Includes an ad, a click tracker link, and an answer of a listed question
+        let html = include_str!("../../test/bing/exit-vim.html");
+        let mut expected = scraped(&[
+            ("stackoverflow", "11828270"),
+            ("askubuntu", "24406"),
+            ("stackoverflow", "25919461"),
+        ]);
+        expected.insert_answer(
+            "stackoverflow",
+            String::from("11828270"),
+            String::from("11828573"),
+        );
+        assert_eq!(
+            Bing::default().parse(html, &exit_vim_sites(), 3).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_brave_parser() {
+        let html = include_str!("../../test/brave/exit-vim.html");
+        let mut expected = scraped(&[
+            ("askubuntu", "24406"),
+            ("stackoverflow", "11828270"),
+            ("stackoverflow", "25919461"),
+        ]);
+        expected.insert_answer(
+            "stackoverflow",
+            String::from("11828270"),
+            String::from("11828573"),
+        );
+        assert_eq!(
+            Brave::default().parse(html, &exit_vim_sites(), 3).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_startpage_parser() {
+        let html = include_str!("../../test/startpage/exit-vim.html");
+        assert_eq!(
+            Startpage::default()
+                .parse(html, &exit_vim_sites(), 3)
+                .unwrap(),
+            scraped(&[
+                ("stackoverflow", "25919461"),
+                ("stackoverflow", "11828270"),
+                ("askubuntu", "24406"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_searxng_url() {
+        let q = "how do I exit vim?";
+        let sites = vec![String::from("stackoverflow.com")];
+        let searxng = Searxng::new(Some("https://searx.example.com/")).unwrap();
+        assert_eq!(
+            searxng.get_url(q, &sites, 1).as_str(),
+            "https://searx.example.com/search\
+            ?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim&format=json"
+        );
+        let searxng = Searxng::new(Some("http://localhost:8888/searx")).unwrap();
+        assert_eq!(
+            searxng.get_url(q, &sites, 2).as_str(),
+            "http://localhost:8888/searx/search\
+            ?q=%28site%3Astackoverflow.com%29+how+do+I+exit+vim&format=json&pageno=2"
+        );
+        assert!(matches!(
+            Searxng::new(None),
+            Err(Error::MissingConfig(s)) if s == "searxng_url"
+        ));
+    }
+
+    #[test]
+    fn test_searxng_parser() {
+        let json = include_str!("../../test/searxng/exit-vim.json");
+        let searxng = Searxng::new(Some("http://localhost:8888")).unwrap();
+        let mut expected = scraped(&[
+            ("stackoverflow", "11828270"),
+            ("askubuntu", "24406"),
+            ("stackoverflow", "25919461"),
+        ]);
+        expected.insert_answer(
+            "stackoverflow",
+            String::from("11828270"),
+            String::from("11828573"),
+        );
+        assert_eq!(searxng.parse(json, &exit_vim_sites(), 3).unwrap(), expected);
+        // JSON output is disabled by default on SearXNG instances
+        let html = include_str!("../../test/startpage/exit-vim.html");
+        assert!(matches!(
+            searxng.parse(html, &exit_vim_sites(), 3),
+            Err(Error::Scraping(_))
+        ));
+    }
+
+    /// DuckDuckGo, as it would be defined in the config file
+    fn custom_duckduckgo() -> CustomEngine {
+        CustomEngine {
+            name: String::from("ddg"),
+            url: String::from("https://duckduckgo.com/?q={sites}%20{query}&s={page}"),
+            selector: String::from("a.result__a"),
+            href_attribute: None,
+            redirect_param: Some(String::from("uddg")),
+            blocked: Some(String::from("#b_captcha")),
+        }
+    }
+
+    #[test]
+    fn test_custom_url() {
+        let q = "how do I exit vim?";
+        let sites = vec![
+            String::from("stackoverflow.com"),
+            String::from("askubuntu.com"),
+        ];
+        let custom = Custom::new(&custom_duckduckgo()).unwrap();
+        assert!(custom.paginates());
+        assert_eq!(
+            custom.get_url(q, &sites, 2).as_str(),
+            "https://duckduckgo.com/\
+            ?q=%28site%3Astackoverflow%2Ecom%20OR%20site%3Aaskubuntu%2Ecom%29\
+            %20how%20do%20I%20exit%20vim&s=2"
+        );
+
+        let invalid = |engine: CustomEngine| match Custom::new(&engine) {
+            Err(Error::InvalidEngine(name, _)) | Err(Error::InvalidUrl(name)) => name,
+            _ => String::from("valid"),
+        };
+        let engine = CustomEngine {
+            url: String::from("https://duckduckgo.com/?q={sites}"),
+            ..custom_duckduckgo()
+        };
+        assert_eq!(invalid(engine), "ddg");
+        let engine = CustomEngine {
+            selector: String::from("a..result"),
+            ..custom_duckduckgo()
+        };
+        assert_eq!(invalid(engine), "ddg");
+        let engine = CustomEngine {
+            url: String::from("duckduckgo?q={query}"),
+            ..custom_duckduckgo()
+        };
+        assert_eq!(invalid(engine), "duckduckgo?q={query}");
+    }
+
+    #[test]
+    fn test_custom_parser() {
+        let sites = exit_vim_sites();
+        let custom = Custom::new(&custom_duckduckgo()).unwrap();
+        // Same as the built in scraper
+        let html = include_str!("../../test/duckduckgo/exit-vim.html");
+        assert_eq!(
+            custom.parse(html, &sites, 3).unwrap(),
+            DuckDuckGo::default().parse(html, &sites, 3).unwrap()
+        );
+
+        let html = include_str!("../../test/bing/captcha.html");
+        assert!(matches!(
+            custom.parse(html, &sites, 3),
+            Err(Error::Scraping(s)) if s == "ddg blocked this request"
+        ));
+
+        // Links may keep their URL in another attribute
+        let engine = CustomEngine {
+            selector: String::from(".result"),
+            href_attribute: Some(String::from("data-url")),
+            redirect_param: None,
+            ..custom_duckduckgo()
+        };
+        let html = r#"<div class="result" data-url="https://askubuntu.com/q/24406"></div>"#;
+        assert_eq!(
+            Custom::new(&engine)
+                .unwrap()
+                .parse(html, &sites, 3)
+                .unwrap(),
+            scraped(&[("askubuntu", "24406")])
+        );
+    }
+
+    #[test]
+    fn test_answer_links_parser() {
+        let html = r#"
+            <a href="https://stackoverflow.com/questions/1/slug/2#2">answer to 1</a>
+            <a href="https://stackoverflow.com/a/5/1234">share link</a>
+            <a href="https://stackoverflow.com/questions/1/slug">question 1</a>
+            <a href="https://stackoverflow.com/a/5">share link again</a>
+            <a href="https://askubuntu.com/questions/3/slug/4#4">answer to 3</a>"#;
+        let data = Google::default().parse(html, &exit_vim_sites(), 3).unwrap();
+        let mut expected = scraped(&[("stackoverflow", "1")]);
+        expected.insert_question("askubuntu", String::from("3"), 2);
+        expected.insert_answer("stackoverflow", String::from("1"), String::from("2"));
+        expected.insert_answer("askubuntu", String::from("3"), String::from("4"));
+        expected
+            .answer_links
+            .insert((String::from("stackoverflow"), String::from("5")), 1);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_captcha_blockers() {
+        let sites = exit_vim_sites();
+        let blocked = |result: Result<ScrapedData>, engine: &str| {
+            assert!(matches!(result, Err(Error::Captcha(s)) if s == engine));
+        };
+        let html = include_str!("../../test/bing/captcha.html");
+        blocked(Bing::default().parse(html, &sites, 3), "Bing");
+        let html = include_str!("../../test/brave/captcha.html");
+        blocked(Brave::default().parse(html, &sites, 3), "Brave Search");
+        let html = include_str!("../../test/startpage/captcha.html");
+        blocked(Startpage::default().parse(html, &sites, 3), "Startpage");
+    }
+
+    #[test]
+    fn test_bing_redirect_target() {
+        let href =
+            "https://www.bing.com/ck/a?!&&p=abc&u=a1aHR0cHM6Ly9zdGFja292ZXJmbG93LmNvbS9xLzE&ntb=1";
+        assert_eq!(bing_redirect_target(href), "https://stackoverflow.com/q/1");
+        let href = "https://stackoverflow.com/q/1";
+        assert_eq!(bing_redirect_target(href), href);
+    }
+
+    #[test]
+    fn test_google_blocker() {
+        let sites = exit_vim_sites();
+        let html = include_str!("../../test/google/captcha.html");
+        assert!(matches!(
+            Google::default().parse(html, &sites, 3),
+            Err(Error::Captcha(s)) if s == "Google"
+        ));
+        let html = include_str!("../../test/google/consent.html");
+        assert!(matches!(
+            Google::default().parse(html, &sites, 3),
+            Err(Error::ConsentRequired(s)) if s == "Google"
+        ));
+        // Genuinely no results isn't an error here
+        let html = include_str!("../../test/google/no-results.html");
+        assert_eq!(
+            Google::default().parse(html, &sites, 3).unwrap(),
+            scraped(&[])
+        );
+    }
+
     #[test]
     fn test_duckduckgo_blocker() -> Result<(), String> {
         let html = include_str!("../../test/duckduckgo/bad-user-agent.html");
@@ -324,7 +1252,7 @@ mod tests {
             String::from("stackoverflow.com"),
         );
 
-        match DuckDuckGo.parse(html, &sites, 2) {
+        match DuckDuckGo::default().parse(html, &sites, 2) {
             Err(Error::Scraping(s)) if s == *"DuckDuckGo blocked this request" => Ok(()),
             _ => Err(String::from("Failed to detect DuckDuckGo blocker")),
         }
@@ -357,5 +1285,44 @@ mod tests {
         let site_url = "meta.stackexchange.com";
         let input = "/l/?kh=-1&uddg=https://math.meta.stackexchange.com/q/11828270";
         assert_eq!(question_url_to_id(site_url, input), None);
+
+        // Full answer link
+        let site_url = "stackoverflow.com";
+        let input =
+            "https://stackoverflow.com/questions/11828270/how-do-i-exit-vim/11828573#11828573";
+        assert_eq!(question_url_to_id(site_url, input).unwrap(), "11828270");
+    }
+
+    #[test]
+    fn test_post_url_to_id() {
+        let site_url = "stackoverflow.com";
+        let input = "https://stackoverflow.com/questions/11828270/how-do-i-exit-vim";
+        assert_eq!(
+            post_url_to_id(site_url, input),
+            Some(PostLink::Question(String::from("11828270")))
+        );
+
+        let input =
+            "https://stackoverflow.com/questions/11828270/how-do-i-exit-vim/11828573#11828573";
+        assert_eq!(
+            post_url_to_id(site_url, input),
+            Some(PostLink::Answer {
+                question_id: Some(String::from("11828270")),
+                answer_id: String::from("11828573"),
+            })
+        );
+
+        // Share links include the user id
+        let input = "https://stackoverflow.com/a/11828573/1234";
+        assert_eq!(
+            post_url_to_id(site_url, input),
+            Some(PostLink::Answer {
+                question_id: None,
+                answer_id: String::from("11828573"),
+            })
+        );
+
+        let input = "https://math.stackoverflow.com/a/11828573";
+        assert_eq!(post_url_to_id(site_url, input), None);
     }
 }
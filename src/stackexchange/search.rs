@@ -1,32 +1,69 @@
-use futures::stream::StreamExt;
+use futures::stream::{Stream, StreamExt};
 use rayon::prelude::*;
 use reqwest::header;
 use reqwest::Client;
-use std::sync::Arc;
+use reqwest::StatusCode;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinError;
 
-use crate::config::{Config, SearchEngine};
+use crate::config::{Config, SearchEngine, SearchFilters, SearchSort};
 use crate::error::{Error, Result};
 use crate::tui::markdown;
 use crate::tui::markdown::Markdown;
 
-use super::api::{Answer, Api, Question};
+use super::api::{Answer, Api, Comment, Id, PostKey, Question};
+use super::http;
 use super::local_storage::SiteMap;
-use super::scraper::{DuckDuckGo, Google, ScrapedData, Scraper};
+use super::scraper::{
+    Bing, Brave, Custom, DuckDuckGo, Google, PostLink, ScrapedData, Scraper, Searxng, Startpage,
+};
 
 /// Limit on concurrent requests (gets passed to `buffer_unordered`)
 const CONCURRENT_REQUESTS_LIMIT: usize = 8;
 
+/// Reciprocal rank fusion constant, dampening the weight of top ranks so that
+/// questions found by several engines beat those ranked first by just one
+const RRF_K: f64 = 60.0;
+
+/// Query words too common to require in titles with `title_only`
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how",
+    "i", "if", "in", "is", "it", "my", "of", "on", "or", "the", "to", "what", "when", "where",
+    "which", "why", "with",
+];
+
+/// Warn when the remaining daily StackExchange API quota drops below this
+const QUOTA_WARNING_THRESHOLD: u32 = 100;
+
 /// This structure provides methods to search queries and get StackExchange
 /// questions/answers in return.
 // TODO this really needs a better name...
 #[derive(Debug, Clone)]
 pub struct Search {
     pub api: Api,
+    /// Client for search engine requests
+    client: Client,
     pub config: Config,
     pub query: String,
     pub site_map: Arc<SiteMap>,
+    /// Search engine that produced the latest results
+    engine: Arc<Mutex<Option<SearchEngine>>>,
+    /// Non-fatal problems encountered while searching, e.g. a failed site
+    warnings: Arc<Mutex<Vec<String>>>,
+    /// Where to stream each site's questions as they arrive, if anywhere
+    batches: Option<UnboundedSender<SiteBatch>>,
 }
 
+/// Questions from a single site
+type SiteQuestions = (String, Result<Vec<Question<String>>>);
+
+/// Questions from a single site, streamed as soon as they arrive; empty if
+/// the site failed
+pub type SiteBatch = (String, Vec<Question<String>>);
+
 #[derive(Debug, Clone)]
 pub struct LuckyAnswer {
     /// Preprocessed markdown content
@@ -36,19 +73,84 @@ pub struct LuckyAnswer {
 }
 
 impl Search {
-    pub fn new(config: Config, site_map: Arc<SiteMap>, query: String) -> Self {
-        let api = Api::new(config.api_key.clone());
-        Search {
+    pub fn new(config: Config, site_map: Arc<SiteMap>, query: String) -> Result<Self> {
+        let api = Api::new(&config)?;
+        let client = http::client_builder(&config)?.build()?;
+        Ok(Search {
             api,
+            client,
             config,
             query,
             site_map,
+            engine: Arc::new(Mutex::new(None)),
+            warnings: Arc::new(Mutex::new(Vec::new())),
+            batches: None,
+        })
+    }
+
+    /// Search engine that produced the latest results, if any
+    pub fn engine(&self) -> Option<SearchEngine> {
+        self.engine.lock().unwrap().clone()
+    }
+
+    /// Take the warnings accumulated since they were last taken
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+
+    /// Tags to search `site` with; the project tag only applies on StackOverflow
+    fn site_tags(&self, site: &str) -> Vec<String> {
+        let mut tags = self.config.tags.clone();
+        if site == "stackoverflow" {
+            tags.extend(self.config.project_tag.clone());
+        }
+        tags
+    }
+
+    /// Query for search engines, with any tags as `[tag]` terms. The project
+    /// tag is left out when searching sites besides StackOverflow.
+    fn tagged_query(&self) -> String {
+        let tags = match self.config.sites.as_slice() {
+            [site] => self.site_tags(site),
+            _ => self.config.tags.clone(),
+        };
+        tags.iter()
+            .map(|tag| format!("[{tag}]"))
+            .chain(std::iter::once(self.query.clone()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn warn(&self, warning: String) {
+        log::warn!("{warning}");
+        self.warnings.lock().unwrap().push(warning);
+    }
+
+    /// Warning that the daily SE API quota is running low, as of the latest
+    /// response
+    pub fn quota_warning(&self) -> Option<String> {
+        let quota = self
+            .api
+            .quota_remaining()
+            .filter(|q| *q < QUOTA_WARNING_THRESHOLD)?;
+        Some(format!(
+            "Only {quota} StackExchange API requests remain in today's quota. \
+            If you aren't already, consider setting your own key with `so --set-api-key`"
+        ))
+    }
+
+    /// Add a warning, shown alongside the results, if the daily SE API quota
+    /// is running low
+    pub fn warn_low_quota(&self) {
+        if let Some(warning) = self.quota_warning() {
+            self.warn(warning);
         }
     }
 
-    /// Search query and get the top answer body
+    /// Search query and get the answer that the top result linked to, or
+    /// else the top answer
     ///
-    /// For StackExchange engine, use only the first configured site,
+    /// For StackExchange (primary) engine, use only the first configured site,
     /// since, parodoxically, sites with the worst results will finish
     /// executing first, because there's less data to retrieve.
     ///
@@ -57,7 +159,12 @@ impl Search {
         let original_config = self.config.clone();
         // Temp set lucky config
         self.config.limit = 1;
-        if let SearchEngine::StackExchange = self.config.search_engine {
+        if let Some(
+            SearchEngine::StackExchange
+            | SearchEngine::StackExchangeSimilar
+            | SearchEngine::StackExchangeExcerpts,
+        ) = self.config.search_engine.first()
+        {
             self.config.sites.truncate(1);
         }
         // Run search with temp config
@@ -67,99 +174,609 @@ impl Search {
 
         let question = result?.into_iter().next().ok_or(Error::NoResults)?;
 
-        let answer = question.answers.first().cloned().ok_or_else(|| {
-            Error::StackExchange(String::from("Received question with no answers"))
-        })?;
+        let answer = question
+            .linked_answer
+            .and_then(|id| question.answers.iter().find(|a| a.id == id))
+            .or_else(|| question.answers.first())
+            .cloned()
+            .ok_or_else(|| {
+                Error::StackExchange(String::from("Received question with no answers"))
+            })?;
 
         Ok(LuckyAnswer { answer, question })
     }
 
-    /// Search and parse to Markdown for TUI
-    pub async fn search_md(&self) -> Result<Vec<Question<Markdown>>> {
-        Ok(parse_markdown(self.search().await?))
+    /// Search a specific (1-indexed) page of results and parse to Markdown
+    /// for TUI
+    pub async fn search_md_page(&self, page: u16) -> Result<Vec<Question<Markdown>>> {
+        let qs = self.search_page(page).await?;
+        Ok(self.prepare_md(qs))
     }
 
-    /// Search using the configured search engine
+    /// Parse questions to Markdown for TUI
+    pub fn prepare_md(&self, qs: Vec<Question<String>>) -> Vec<Question<Markdown>> {
+        parse_markdown(qs)
+    }
+
+    /// Fetch the question of a question or answer link on `site` and parse to
+    /// Markdown for TUI, noting the linked answer, if any.
+    pub async fn open_md(&self, site: &str, link: PostLink) -> Result<Vec<Question<Markdown>>> {
+        let (question_id, answer_id) = match link {
+            PostLink::Question(id) => (id, None),
+            PostLink::Answer {
+                question_id: Some(question_id),
+                answer_id,
+            } => (question_id, Some(answer_id)),
+            PostLink::Answer {
+                question_id: None,
+                answer_id,
+            } => {
+                let question_id = self
+                    .api
+                    .answer_parents(site, std::slice::from_ref(&answer_id))
+                    .await?
+                    .remove(&answer_id)
+                    .ok_or(Error::NoResults)?;
+                (question_id, Some(answer_id))
+            }
+        };
+        let mut qs = self.api.questions(site, vec![question_id]).await?;
+        if qs.is_empty() {
+            return Err(Error::NoResults);
+        }
+        qs[0].linked_answer = answer_id.and_then(|id| id.parse().ok());
+        Ok(parse_markdown(qs))
+    }
+
+    /// Search using the configured search engines
     pub async fn search(&self) -> Result<Vec<Question<String>>> {
-        match self.config.search_engine {
-            SearchEngine::DuckDuckGo => self.search_by_scraper(DuckDuckGo).await,
-            SearchEngine::Google => self.search_by_scraper(Google).await,
-            SearchEngine::StackExchange => self.parallel_search_advanced().await,
+        self.search_page(1).await
+    }
+
+    /// Search using the configured search engines, additionally sending each
+    /// site's questions to `batches` as soon as they arrive. Engines that
+    /// don't search per site, e.g. meta search, send nothing.
+    pub async fn search_streaming(
+        &self,
+        batches: UnboundedSender<SiteBatch>,
+    ) -> Result<Vec<Question<String>>> {
+        let search = Search {
+            batches: Some(batches),
+            ..self.clone()
+        };
+        search.search().await
+    }
+
+    /// Search a specific (1-indexed) page of results using the configured
+    /// search engines, falling back to the next engine in line when one is
+    /// blocked or unreachable. Later pages start with the engine that
+    /// produced the previous page.
+    pub async fn search_page(&self, page: u16) -> Result<Vec<Question<String>>> {
+        let engines = &self.config.search_engine;
+        let start = self
+            .engine()
+            .and_then(|current| engines.iter().position(|e| *e == current))
+            .unwrap_or(0);
+        let mut engines = engines[start..].iter().peekable();
+        while let Some(engine) = engines.next() {
+            match self.search_engine_page(engine, page).await {
+                Err(e) if engines.peek().is_some() && is_fallback_error(&e) => {
+                    log::warn!("Search with {engine} failed, trying the next engine: {e}");
+                }
+                result => {
+                    if result.is_ok() {
+                        *self.engine.lock().unwrap() = Some(engine.clone());
+                    }
+                    return result;
+                }
+            }
         }
-        .and_then(|qs| {
+        Err(Error::NoResults)
+    }
+
+    /// Search a specific (1-indexed) page of results using the given search engine
+    async fn search_engine_page(
+        &self,
+        engine: &SearchEngine,
+        page: u16,
+    ) -> Result<Vec<Question<String>>> {
+        match engine {
+            SearchEngine::DuckDuckGo => {
+                let scraper = DuckDuckGo::new(self.config.duckduckgo_url.as_deref())?;
+                self.search_by_scraper(scraper, page).await
+            }
+            SearchEngine::Google => {
+                let scraper = Google::new(self.config.google_url.as_deref())?;
+                self.search_by_scraper(scraper, page).await
+            }
+            SearchEngine::Bing => {
+                let scraper = Bing::new(self.config.bing_url.as_deref())?;
+                self.search_by_scraper(scraper, page).await
+            }
+            SearchEngine::Brave => {
+                let scraper = Brave::new(self.config.brave_url.as_deref())?;
+                self.search_by_scraper(scraper, page).await
+            }
+            SearchEngine::Startpage => {
+                let scraper = Startpage::new(self.config.startpage_url.as_deref())?;
+                self.search_by_scraper(scraper, page).await
+            }
+            SearchEngine::Searxng => {
+                let scraper = Searxng::new(self.config.searxng_url.as_deref())?;
+                self.search_by_scraper(scraper, page).await
+            }
+            SearchEngine::Custom(name) => {
+                let engine = self
+                    .config
+                    .engines
+                    .iter()
+                    .find(|engine| engine.name == *name)
+                    .ok_or_else(|| Error::UnknownEngine(name.clone()))?;
+                let scraper = Custom::new(engine)?;
+                if page > 1 && !scraper.paginates() {
+                    return Err(Error::NoResults);
+                }
+                self.search_by_scraper(scraper, page).await
+            }
+            SearchEngine::StackExchange
+            | SearchEngine::StackExchangeSimilar
+            | SearchEngine::StackExchangeExcerpts => self.parallel_search_api(engine, page).await,
+            SearchEngine::Meta => self.meta_search(page).await,
+        }
+        .and_then(|mut qs| {
             if qs.is_empty() {
                 Err(Error::NoResults)
             } else {
+                sort_questions(self.config.filters.sort, &mut qs);
                 Ok(qs)
             }
         })
     }
 
     /// Search query at duckduckgo and then fetch the resulting questions from SE.
-    async fn search_by_scraper(&self, scraper: impl Scraper) -> Result<Vec<Question<String>>> {
-        let url = scraper.get_url(&self.query, self.site_map.values());
-        let html = Client::new()
+    async fn search_by_scraper(
+        &self,
+        scraper: impl Scraper,
+        page: u16,
+    ) -> Result<Vec<Question<String>>> {
+        let data = self.scrape(scraper, page).await?;
+        self.parallel_questions(data).await
+    }
+
+    /// Scrape question ids from a page of search engine results
+    async fn scrape(&self, scraper: impl Scraper, page: u16) -> Result<ScrapedData> {
+        let url = scraper.get_url(&self.tagged_query(), self.site_map.values(), page);
+        let request = self
+            .client
             .get(url)
-            .header(header::USER_AGENT, super::USER_AGENT)
-            .send()
-            .await?
-            .text()
-            .await?;
-        let data = scraper.parse(&html, self.site_map.as_ref(), self.config.limit)?;
+            .header(header::USER_AGENT, super::USER_AGENT);
+        let response = http::send(request, self.config.retries).await?;
+        let status = response.status();
+        let html = response.text().await?;
+        let data = scraper.parse(&html, self.site_map.as_ref(), self.config.limit);
+        if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            // Block pages are served with these statuses too, and say more
+            return Err(match data {
+                Err(e @ (Error::Captcha(_) | Error::ConsentRequired(_))) => e,
+                _ => Error::RateLimited {
+                    engine: scraper.name().to_string(),
+                    status: status.as_u16(),
+                },
+            });
+        }
+        let mut data = data?;
+        self.resolve_answer_links(&mut data).await;
         log::debug!("Scraped question IDs: {:#?}", &data.question_ids);
-        self.parallel_questions(data).await
+        Ok(data)
+    }
+
+    /// Add the questions of answer links that lack a question id, looking
+    /// them up per site, up to the result limit. Sites that fail only lose
+    /// their answer links.
+    async fn resolve_answer_links(&self, data: &mut ScrapedData) {
+        let mut answer_ids: HashMap<String, Vec<String>> = HashMap::new();
+        for (site, id) in data.answer_links.keys() {
+            answer_ids.entry(site.clone()).or_default().push(id.clone());
+        }
+        let parents = answer_ids.into_iter().map(|(site, ids)| async move {
+            let parents = self.api.answer_parents(&site, &ids).await;
+            (site, parents)
+        });
+        for (site, parents) in futures::future::join_all(parents).await {
+            match parents {
+                Ok(parents) => {
+                    for (answer_id, question_id) in parents {
+                        let place = data.answer_links[&(site.clone(), answer_id.clone())];
+                        data.insert_answer(&site, question_id.clone(), answer_id);
+                        data.insert_question(&site, question_id, place);
+                    }
+                }
+                Err(e) => self.warn(format!("Skipped answers linked on {site}: {e}")),
+            }
+        }
+        data.truncate(self.config.limit as usize);
+    }
+
+    /// Search Google, DuckDuckGo, and StackExchange concurrently and merge
+    /// their rankings with reciprocal rank fusion. Engines that fail are
+    /// skipped, unless they all fail.
+    async fn meta_search(&self, page: u16) -> Result<Vec<Question<String>>> {
+        let google = async {
+            let scraper = Google::new(self.config.google_url.as_deref())?;
+            self.scrape(scraper, page).await
+        };
+        let duckduckgo = async {
+            let scraper = DuckDuckGo::new(self.config.duckduckgo_url.as_deref())?;
+            self.scrape(scraper, page).await
+        };
+        // Results are only meaningful once fused, so don't stream them
+        let quiet = Search {
+            batches: None,
+            ..self.clone()
+        };
+        let (google, duckduckgo, stackexchange) = futures::join!(
+            google,
+            duckduckgo,
+            quiet.parallel_search_api(&SearchEngine::StackExchange, page)
+        );
+
+        let mut rankings = Vec::new();
+        let mut questions = HashMap::new();
+        let mut answer_ids = HashMap::new();
+        let mut error = None;
+        for (engine, result) in [
+            (SearchEngine::Google, google),
+            (SearchEngine::DuckDuckGo, duckduckgo),
+        ] {
+            match result {
+                Ok(data) => {
+                    rankings.push((engine, scraped_ranking(&data)));
+                    for (key, answer_id) in data.answer_ids {
+                        answer_ids.entry(key).or_insert(answer_id);
+                    }
+                }
+                Err(e) => {
+                    self.warn(format!("Skipped {engine}: {e}"));
+                    error = Some(e);
+                }
+            }
+        }
+        match stackexchange {
+            Ok(qs) => {
+                let ranking = qs.iter().map(Question::key).collect();
+                rankings.push((SearchEngine::StackExchange, ranking));
+                questions.extend(qs.into_iter().map(|q| (q.key(), q)));
+            }
+            Err(e) => {
+                self.warn(format!("Skipped {}: {e}", SearchEngine::StackExchange));
+                error = Some(e);
+            }
+        }
+        if rankings.is_empty() {
+            // Every engine failed, so these aren't mere warnings
+            self.take_warnings();
+            return Err(error.unwrap_or(Error::NoResults));
+        }
+
+        let mut fused = fuse_rankings(rankings);
+        fused.truncate(self.config.limit as usize);
+
+        // Fetch the questions that only the scrapers found
+        let mut missing = ScrapedData::default();
+        for (ix, ((site, id), _)) in fused.iter().enumerate() {
+            if !questions.contains_key(&(site.clone(), *id)) {
+                missing.insert_question(site, id.to_string(), ix);
+            }
+        }
+        if !missing.question_ids.is_empty() {
+            let qs = quiet.parallel_questions(missing).await?;
+            questions.extend(qs.into_iter().map(|q| (q.key(), q)));
+        }
+
+        Ok(fused
+            .into_iter()
+            .filter_map(|(key, engines)| {
+                let mut q = questions.remove(&key)?;
+                q.engines = engines;
+                q.linked_answer = linked_answer(&answer_ids, &key);
+                Some(q)
+            })
+            .collect())
     }
 
     /// Parallel requests against the SE question endpoint across all sites in data.
-    // TODO I'm sure there is a way to DRY the following two functions
     async fn parallel_questions(&self, data: ScrapedData) -> Result<Vec<Question<String>>> {
         let ScrapedData {
             question_ids,
             ordering,
+            answer_ids,
+            ..
         } = data;
-        futures::stream::iter(question_ids)
+        let results = futures::stream::iter(question_ids)
             .map(|(site, ids)| {
                 let api = self.api.clone();
-                tokio::spawn(async move { api.questions(&site, ids).await })
-            })
-            .buffer_unordered(CONCURRENT_REQUESTS_LIMIT)
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .map(|r| r.map_err(Error::from).and_then(|x| x))
-            .collect::<Result<Vec<Vec<_>>>>()
-            .map(|v| {
-                let mut qs: Vec<Question<String>> = v.into_iter().flatten().collect();
-                qs.sort_unstable_by_key(|q| ordering.get(&q.id.to_string()).unwrap());
-                qs
+                let filters = self.config.filters.clone();
+                let query = self.query.clone();
+                tokio::spawn(async move {
+                    let qs = api
+                        .questions(&site, ids)
+                        .await
+                        .map(|qs| filter_questions(&filters, &query, qs));
+                    (site, qs)
+                })
             })
+            .buffer_unordered(CONCURRENT_REQUESTS_LIMIT);
+        let mut qs = self.collect_sites(results).await?;
+        for q in qs.iter_mut() {
+            q.linked_answer = linked_answer(&answer_ids, &q.key());
+        }
+        qs.sort_by_cached_key(|q| {
+            let (site, id) = q.key();
+            ordering
+                .get(&(site, id.to_string()))
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+        Ok(qs)
     }
 
-    /// Parallel requests against the SE search/advanced endpoint across all configured sites
-    async fn parallel_search_advanced(&self) -> Result<Vec<Question<String>>> {
-        futures::stream::iter(self.config.sites.clone())
+    /// Parallel requests against the SE endpoint of the given StackExchange
+    /// engine mode across all configured sites
+    async fn parallel_search_api(
+        &self,
+        engine: &SearchEngine,
+        page: u16,
+    ) -> Result<Vec<Question<String>>> {
+        let results = futures::stream::iter(self.config.sites.clone())
             .map(|site| {
                 let api = self.api.clone();
                 let limit = self.config.limit;
                 let query = self.query.clone();
-                tokio::spawn(async move { api.search_advanced(&query, &site, limit).await })
+                let tags = self.site_tags(&site);
+                let filters = self.config.filters.clone();
+                let engine = engine.clone();
+                tokio::spawn(async move {
+                    let qs = match engine {
+                        SearchEngine::StackExchangeSimilar => {
+                            api.similar(&query, &site, &tags, &filters, limit, page)
+                                .await
+                        }
+                        SearchEngine::StackExchangeExcerpts => {
+                            api.search_excerpts(&query, &site, &tags, &filters, limit, page)
+                                .await
+                        }
+                        _ => {
+                            api.search_advanced(&query, &site, &tags, &filters, limit, page)
+                                .await
+                        }
+                    };
+                    // The API matches titles itself, perhaps more loosely,
+                    // but can't apply every other filter on every endpoint
+                    let filters = SearchFilters {
+                        title_only: false,
+                        ..filters
+                    };
+                    let qs = qs.map(|qs| filter_questions(&filters, &query, qs));
+                    (site, qs)
+                })
             })
-            .buffer_unordered(CONCURRENT_REQUESTS_LIMIT)
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .map(|r| r.map_err(Error::from).and_then(|x| x))
-            .collect::<Result<Vec<Vec<_>>>>()
-            .map(|v| {
-                let mut qs: Vec<Question<String>> = v.into_iter().flatten().collect();
-                if self.config.sites.len() > 1 {
-                    qs.sort_unstable_by_key(|q| -q.score);
+            .buffer_unordered(CONCURRENT_REQUESTS_LIMIT);
+        let mut qs = self.collect_sites(results).await?;
+        if self.config.sites.len() > 1 {
+            if *engine == SearchEngine::StackExchange {
+                qs.sort_unstable_by_key(|q| -q.score);
+            } else {
+                // Scores don't reflect how well these modes matched, so
+                // interleave the sites' results by their rank instead, in
+                // the configured order of sites
+                let mut ranks: HashMap<String, usize> = HashMap::new();
+                let mut ranked = qs
+                    .into_iter()
+                    .map(|q| {
+                        let site = q.key().0;
+                        let position = self.config.sites.iter().position(|s| *s == site);
+                        let rank = ranks.entry(site).or_default();
+                        *rank += 1;
+                        ((*rank, position), q)
+                    })
+                    .collect::<Vec<_>>();
+                ranked.sort_by_key(|(rank, _)| *rank);
+                qs = ranked.into_iter().map(|(_, q)| q).collect();
+            }
+        }
+        Ok(qs)
+    }
+
+    /// Collect per-site results as they arrive, streaming them to `batches`
+    /// if set. Keeps the questions from the sites that succeeded, recording
+    /// failed sites as warnings. Fails only if no site succeeded.
+    async fn collect_sites(
+        &self,
+        mut results: impl Stream<Item = std::result::Result<SiteQuestions, JoinError>> + Unpin,
+    ) -> Result<Vec<Question<String>>> {
+        let mut qs = Vec::new();
+        let mut failures = Vec::new();
+        let mut succeeded = false;
+        while let Some(result) = results.next().await {
+            if let (Some(batches), Ok((site, site_qs))) = (&self.batches, &result) {
+                let batch = site_qs.as_ref().cloned().unwrap_or_default();
+                // The receiver may be gone, but the search result is still useful
+                let _ = batches.send((site.clone(), batch));
+            }
+            match result {
+                Ok((_, Ok(site_qs))) => {
+                    succeeded = true;
+                    qs.extend(site_qs);
                 }
-                qs
+                Ok((site, Err(e))) => failures.push((site, e)),
+                Err(e) => failures.push((String::from("unknown site"), Error::from(e))),
+            }
+        }
+        if !succeeded {
+            if let Some((_, e)) = failures.into_iter().next() {
+                return Err(e);
+            }
+        } else {
+            for (site, e) in failures {
+                self.warn(format!("Skipped {site}: {e}"));
+            }
+        }
+        Ok(qs)
+    }
+
+    /// Fetch the comments on a question and its answers, parsed to Markdown
+    /// for TUI and keyed by post id. Comments cost two requests per question,
+    /// so the TUI only fetches them once they're expanded.
+    pub async fn comments_md(
+        &self,
+        site: &str,
+        question_id: Id,
+        answer_ids: &[Id],
+    ) -> Result<HashMap<Id, Vec<Comment<Markdown>>>> {
+        let mut cs = self.api.question_comments(site, &[question_id]).await?;
+        cs.extend(self.api.answer_comments(site, answer_ids).await?);
+        // Question and answer ids share the post id space within a site
+        let mut comments: HashMap<Id, Vec<Comment<Markdown>>> = HashMap::new();
+        for c in parse_comments(cs) {
+            comments.entry(c.post_id).or_default().push(c);
+        }
+        Ok(comments)
+    }
+}
+
+/// Scraped questions, in the order of the search results
+fn scraped_ranking(data: &ScrapedData) -> Vec<PostKey> {
+    let mut ranked = data
+        .question_ids
+        .iter()
+        .flat_map(|(site, ids)| {
+            ids.iter().filter_map(move |id| {
+                let rank = data.ordering.get(&(site.clone(), id.clone()))?;
+                Some((*rank, (site.clone(), id.parse().ok()?)))
             })
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_unstable_by_key(|(rank, _)| *rank);
+    ranked.into_iter().map(|(_, key)| key).collect()
+}
+
+/// Merge engine rankings with reciprocal rank fusion: each question scores
+/// `1 / (RRF_K + rank)` for every engine that surfaced it. Returns questions
+/// by descending score, along with the engines that surfaced them.
+fn fuse_rankings(rankings: Vec<(SearchEngine, Vec<PostKey>)>) -> Vec<(PostKey, Vec<SearchEngine>)> {
+    let mut fused: HashMap<PostKey, (f64, usize, Vec<SearchEngine>)> = HashMap::new();
+    let mut first_seen = 0;
+    for (engine, ranking) in rankings {
+        for (rank, key) in ranking.into_iter().enumerate() {
+            let entry = fused.entry(key).or_insert_with(|| {
+                first_seen += 1;
+                (0.0, first_seen, Vec::new())
+            });
+            entry.0 += 1.0 / (RRF_K + rank as f64 + 1.0);
+            if !entry.2.contains(&engine) {
+                entry.2.push(engine.clone());
+            }
+        }
+    }
+    let mut fused = fused.into_iter().collect::<Vec<_>>();
+    // Break ties by order of appearance, for a deterministic ranking
+    fused.sort_unstable_by(|(_, (a, a_seen, _)), (_, (b, b_seen, _))| {
+        b.total_cmp(a).then(a_seen.cmp(b_seen))
+    });
+    fused
+        .into_iter()
+        .map(|(key, (_, _, engines))| (key, engines))
+        .collect()
+}
+
+/// Whether an engine's failure is worth falling back to the next engine for,
+/// i.e. it was blocked or unreachable
+fn is_fallback_error(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Scraping(_)
+            | Error::Captcha(_)
+            | Error::ConsentRequired(_)
+            | Error::RateLimited { .. }
+            | Error::Http { .. }
+            | Error::Reqwest(_)
+    )
+}
+
+/// Keep the questions that pass `filters`, for engines that can't apply them.
+/// Questions lacking the metadata to filter on are kept.
+fn filter_questions(
+    filters: &SearchFilters,
+    query: &str,
+    qs: Vec<Question<String>>,
+) -> Vec<Question<String>> {
+    let terms: Vec<String> = if filters.title_only {
+        words(query)
+            .into_iter()
+            .filter(|word| !STOPWORDS.contains(&word.as_str()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    qs.into_iter()
+        .filter(|q| passes_filters(filters, &terms, q))
+        .collect()
+}
+
+/// Whether a question passes `filters`, with `terms` required in its title
+fn passes_filters(filters: &SearchFilters, terms: &[String], q: &Question<String>) -> bool {
+    let created = q.creation_date;
+    if filters.accepted && !q.answers.iter().any(|a| a.is_accepted) {
+        return false;
+    }
+    if matches!(filters.min_score, Some(min) if q.score < min) {
+        return false;
+    }
+    if filters.exclude_closed && q.closed_reason.is_some() {
+        return false;
+    }
+    if matches!(filters.from_date.zip(created), Some((date, created)) if created < date.start()) {
+        return false;
+    }
+    if matches!(filters.to_date.zip(created), Some((date, created)) if created > date.end()) {
+        return false;
+    }
+    terms.is_empty() || {
+        let title = words(&q.title);
+        terms.iter().all(|term| title.contains(term))
+    }
+}
+
+/// Lowercase words of some text, for matching a query against titles
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Sort questions in the given order; relevance keeps the search ranking
+fn sort_questions(sort: SearchSort, qs: &mut [Question<String>]) {
+    match sort {
+        SearchSort::Relevance => {}
+        SearchSort::Votes => qs.sort_by_key(|q| Reverse(q.score)),
+        SearchSort::Activity => qs.sort_by_key(|q| Reverse(q.last_edit_date.or(q.creation_date))),
+        SearchSort::Creation => qs.sort_by_key(|q| Reverse(q.creation_date)),
     }
 }
 
+/// The answer that search results linked to for a question, if any
+fn linked_answer(answer_ids: &HashMap<(String, String), String>, key: &PostKey) -> Option<Id> {
+    let (site, id) = key;
+    answer_ids
+        .get(&(site.clone(), id.to_string()))?
+        .parse()
+        .ok()
+}
+
 /// Parse all markdown fields
 /// This only happens for content going into the cursive TUI (not lucky prompt)
 fn parse_markdown(qs: Vec<Question<String>>) -> Vec<Question<Markdown>> {
@@ -176,6 +793,10 @@ fn parse_markdown(qs: Vec<Question<String>>) -> Vec<Question<Markdown>> {
                         id: a.id,
                         score: a.score,
                         is_accepted: a.is_accepted,
+                        owner: a.owner,
+                        creation_date: a.creation_date,
+                        last_edit_date: a.last_edit_date,
+                        comments: parse_comments(a.comments),
                     }
                 })
                 .collect::<Vec<_>>();
@@ -186,15 +807,66 @@ fn parse_markdown(qs: Vec<Question<String>>) -> Vec<Question<Markdown>> {
                 score: q.score,
                 title: q.title,
                 site: q.site,
+                engines: q.engines,
+                linked_answer: q.linked_answer,
+                excerpt: q.excerpt.map(markdown::parse),
+                tags: q.tags,
+                owner: q.owner,
+                creation_date: q.creation_date,
+                last_edit_date: q.last_edit_date,
+                view_count: q.view_count,
+                closed_reason: q.closed_reason,
+                comments: parse_comments(q.comments),
             }
         })
         .collect::<Vec<_>>()
 }
 
-// TODO find a query that returns no results so that I can test it and
-// differentiate it from a blocked request
+fn parse_comments(cs: Vec<Comment<String>>) -> Vec<Comment<Markdown>> {
+    cs.into_iter()
+        .map(|c| Comment {
+            body: markdown::parse(c.body),
+            id: c.id,
+            post_id: c.post_id,
+            score: c.score,
+            owner: c.owner,
+            creation_date: c.creation_date,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuse_rankings() {
+        let key = |site: &str, id: Id| (site.to_string(), id);
+        let rankings = vec![
+            (
+                SearchEngine::Google,
+                vec![key("stackoverflow", 1), key("stackoverflow", 2)],
+            ),
+            (
+                SearchEngine::DuckDuckGo,
+                vec![key("superuser", 3), key("stackoverflow", 2)],
+            ),
+            (SearchEngine::StackExchange, vec![key("superuser", 1)]),
+        ];
+        let fused = fuse_rankings(rankings);
+        assert_eq!(
+            fused,
+            vec![
+                (
+                    key("stackoverflow", 2),
+                    vec![SearchEngine::Google, SearchEngine::DuckDuckGo]
+                ),
+                (key("stackoverflow", 1), vec![SearchEngine::Google]),
+                (key("superuser", 3), vec![SearchEngine::DuckDuckGo]),
+                (key("superuser", 1), vec![SearchEngine::StackExchange]),
+            ]
+        );
+    }
 
     #[test]
     fn test_duckduckgo_response() {
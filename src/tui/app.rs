@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write as _;
 use std::io;
 use std::io::Write;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
 use cursive::event::{Event, Key};
 use cursive::theme::{BaseColor, Color, Effect, Style};
@@ -11,6 +14,9 @@ use cursive::utils::span::SpannedString;
 use cursive::views::{Dialog, LinearLayout, TextView, ViewRef};
 use cursive::Cursive;
 use cursive::XY;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::JoinHandle;
 
 use super::markdown;
 use super::markdown::Markdown;
@@ -18,34 +24,172 @@ use super::views::{
     LayoutView, ListView, MdView, Name, TempView, Vimable, NAME_ANSWER_LIST, NAME_ANSWER_VIEW,
     NAME_FULL_LAYOUT, NAME_QUESTION_LIST, NAME_QUESTION_VIEW, NAME_TEMP_MSG,
 };
-use crate::config::Config;
-use crate::error::Result;
-use crate::stackexchange::{Answer, Id, Question, Search, SiteMap};
+use crate::config::{Config, SearchEngine};
+use crate::error::{Error, Result};
+use crate::stackexchange::{
+    Answer, Comment, Id, PostKey, PostLink, Question, Search, SiteBatch, User,
+};
+use crate::utils;
 
 pub const NAME_HELP_VIEW: &str = "help_view";
+pub const NAME_HINT_VIEW: &str = "hint_view";
+
+/// Question list value of the "loading more" row; SE ids are never zero
+const LOADING_MORE_KEY: PostKey = (String::new(), 0);
 
 pub struct App {
-    questions: HashMap<Id, Question<Markdown>>,
-    answers: HashMap<Id, Answer<Markdown>>,
-    config: Config,
-    site_map: Arc<SiteMap>,
+    /// Question and answer data, which grows as more pages are loaded
+    data: RwLock<AppData>,
+    /// Current sort of the question list
+    sort: Mutex<QuestionSort>,
+    /// Pagination state of the search results
+    pager: Mutex<Pager>,
+    /// Whether comments are expanded beneath question and answer bodies
+    show_comments: AtomicBool,
+    /// Whether to show the searched query in the hint bar
+    show_query: bool,
+    /// The search behind the results, for loading more of them
+    search: Search,
+    /// Handle to the async runtime, for fetching more results from the TUI
+    runtime: Handle,
+    /// Sites whose first page of results is still streaming in
+    loading_sites: Mutex<Vec<String>>,
+    /// The rest of the first page of results, until the TUI starts streaming it in
+    streaming: Mutex<Option<StreamingSearch>>,
+}
+
+/// A search that has produced its first results, but is still running
+struct StreamingSearch {
+    /// Questions from each site, as they arrive
+    batches: UnboundedReceiver<SiteBatch>,
+    /// All results, in ranked order
+    result: JoinHandle<Result<Vec<Question<String>>>>,
+}
+
+#[derive(Default)]
+struct AppData {
+    /// Questions in the order returned by the search engine
+    ranking: Vec<PostKey>,
+    questions: HashMap<PostKey, Question<Markdown>>,
+    answers: HashMap<PostKey, Answer<Markdown>>,
+    /// Whether the comments of each question and answer have been fetched,
+    /// which only happens once they are expanded
+    comments: HashMap<PostKey, CommentsState>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CommentsState {
+    Loading,
+    Loaded,
+}
+
+struct Pager {
+    /// Next (1-indexed) page of search results to fetch
+    next_page: u16,
+    /// Whether a page is currently being fetched
+    loading: bool,
+    /// Whether the search engine has run out of results
+    exhausted: bool,
+}
+
+/// Available orderings of the question list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuestionSort {
+    /// Search engine ranking
+    #[default]
+    Rank,
+    Score,
+    Answers,
+    Site,
+}
+
+impl QuestionSort {
+    /// The next sort in the cycle
+    fn next(self) -> Self {
+        match self {
+            QuestionSort::Rank => QuestionSort::Score,
+            QuestionSort::Score => QuestionSort::Answers,
+            QuestionSort::Answers => QuestionSort::Site,
+            QuestionSort::Site => QuestionSort::Rank,
+        }
+    }
+}
+
+impl fmt::Display for QuestionSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            QuestionSort::Rank => "rank",
+            QuestionSort::Score => "score",
+            QuestionSort::Answers => "answers",
+            QuestionSort::Site => "site",
+        };
+        write!(f, "{s}")
+    }
 }
 
 impl App {
+    /// Start the search, returning as soon as the first site has results.
+    /// The rest are streamed into the question list once the TUI runs.
     pub async fn from_search(search: Search) -> Result<Self> {
-        let qs = search.search_md().await?;
-        let questions: HashMap<u32, Question<Markdown>> =
-            qs.clone().into_iter().map(|q| (q.id, q)).collect();
-        let answers: HashMap<u32, Answer<Markdown>> = qs
-            .into_iter()
-            .flat_map(|q| q.answers.into_iter().map(|a| (a.id, a)))
-            .collect();
-        Ok(Self {
-            config: search.config,
-            site_map: search.site_map,
-            questions,
-            answers,
-        })
+        let (tx, mut batches) = mpsc::unbounded_channel();
+        let streaming = search.clone();
+        let mut result = tokio::spawn(async move { streaming.search_streaming(tx).await });
+        let mut loading_sites = search.config.sites.clone();
+        loop {
+            tokio::select! {
+                Some((site, qs)) = batches.recv() => {
+                    loading_sites.retain(|s| *s != site);
+                    if qs.is_empty() {
+                        continue;
+                    }
+                    let qs = search.prepare_md(qs);
+                    let app = Self::new(search, qs, false);
+                    app.pager.lock().unwrap().loading = true;
+                    *app.loading_sites.lock().unwrap() = loading_sites;
+                    *app.streaming.lock().unwrap() = Some(StreamingSearch { batches, result });
+                    return Ok(app);
+                }
+                res = &mut result => {
+                    // Finished before streaming anything, e.g. meta search
+                    let qs = search.prepare_md(res??);
+                    return Ok(Self::new(search, qs, false));
+                }
+            }
+        }
+    }
+
+    /// Open a single question from a question or answer link. If the link
+    /// is to an answer, it is selected on startup.
+    pub async fn from_post(search: Search, site: &str, link: PostLink) -> Result<Self> {
+        let qs = search.open_md(site, link).await?;
+        // Nothing more to load, this isn't a search
+        Ok(Self::new(search, qs, true))
+    }
+
+    fn new(search: Search, qs: Vec<Question<Markdown>>, exhausted: bool) -> Self {
+        let mut data = AppData::default();
+        data.extend(qs);
+        Self {
+            data: RwLock::new(data),
+            sort: Mutex::new(QuestionSort::default()),
+            pager: Mutex::new(Pager {
+                next_page: 2,
+                loading: false,
+                exhausted,
+            }),
+            show_comments: AtomicBool::new(false),
+            show_query: false,
+            search,
+            runtime: Handle::current(),
+            loading_sites: Mutex::new(Vec::new()),
+            streaming: Mutex::new(None),
+        }
+    }
+
+    /// Show the searched query in the hint bar, e.g. to check how it was
+    /// normalized
+    pub fn show_query(&mut self) {
+        self.show_query = true;
     }
 
     // TODO a <Mutex> app field that gets auto updated with new selections would be convenient
@@ -62,19 +206,24 @@ impl App {
         let answer_view = MdView::new(Name::AnswerView);
 
         let arc2 = arc.clone();
-        let question_list_view = ListView::new_with_items(
+        let mut question_list_view = ListView::new_with_items(
             Name::QuestionList,
-            arc.questions
-                .clone()
-                .into_values()
-                .map(|q| (preview_question(&q), q.id)),
-            move |s, qid| arc2.question_selected_callback(s, *qid),
+            arc.question_previews(),
+            move |s, key| arc2.question_selected_callback(s, key),
         );
+        question_list_view
+            .get_mut()
+            .set_title(arc.question_list_title());
+        let arc2 = arc.clone();
+        question_list_view
+            .get_mut()
+            .set_on_scroll_end(move |s| arc2.load_more(s));
 
         let arc2 = arc.clone();
-        let answer_list_view = ListView::new(Name::AnswerList, move |s, aid| {
-            let a = arc2.answers.get(aid).unwrap();
-            s.call_on_name(NAME_ANSWER_VIEW, |v: &mut MdView| v.set_content(&a.body));
+        let answer_list_view = ListView::new(Name::AnswerList, move |s, key| {
+            let data = arc2.data.read().unwrap();
+            let content = arc2.answer_content(&data, key);
+            s.call_on_name(NAME_ANSWER_VIEW, |v: &mut MdView| v.set_content(&content));
         });
 
         let main_layout = LayoutView::new(
@@ -85,7 +234,7 @@ impl App {
             answer_view,
         )
         .add_vim_bindings();
-        let hint_text = TextView::new("? help \u{00B7} q quit");
+        let hint_text = TextView::new(arc.hint_text()).with_name(NAME_HINT_VIEW);
 
         siv.add_layer(LinearLayout::vertical().child(main_layout).child(hint_text));
 
@@ -94,6 +243,9 @@ impl App {
             cb(&mut siv)
         }
 
+        temp_warnings_msg(&mut siv, arc.search.take_warnings());
+        arc.stream_results(&mut siv);
+
         // Help / View keymappings
         siv.add_global_callback('?', |s| {
             if let Some(pos) = s.screen_mut().find_layer_from_name(NAME_HELP_VIEW) {
@@ -103,6 +255,34 @@ impl App {
             }
         });
 
+        // Cycle question sort
+        let arc2 = arc.clone();
+        siv.add_global_callback('s', move |s| {
+            {
+                let mut sort = arc2.sort.lock().unwrap();
+                *sort = sort.next();
+            }
+            let cb = s.call_on_name(NAME_QUESTION_LIST, |v: &mut ListView| {
+                v.set_title(arc2.question_list_title());
+                v.reset_with_all(arc2.question_previews())
+            });
+            if let Some(cb) = cb {
+                cb(s)
+            }
+        });
+
+        // Expand / collapse comments
+        let arc2 = arc.clone();
+        siv.add_global_callback('c', move |s| {
+            let expanded = !arc2.show_comments.fetch_xor(true, Ordering::Relaxed);
+            if expanded {
+                if let Some((qkey, _)) = focused_ids(s) {
+                    arc2.load_comments(s, &qkey);
+                }
+            }
+            arc2.refresh_post_views(s);
+        });
+
         // Reload theme
         siv.add_global_callback(Event::CtrlChar('r'), |s| {
             s.load_theme_file(Config::theme_file_path().unwrap())
@@ -116,21 +296,21 @@ impl App {
                 .find_name(NAME_FULL_LAYOUT)
                 .expect("bug: layout view should exist");
             let md = v.get_focused_content();
-            if let Some(mut copy_cmd) = arc2.config.get_copy_cmd() {
+            if let Some(mut copy_cmd) = arc2.search.config.get_copy_cmd() {
                 let res = (|| {
                     let mut child = copy_cmd.spawn().map_err(|e| {
                         if e.kind() == io::ErrorKind::NotFound {
-                            io::Error::new(
-                                io::ErrorKind::Other,
+                            io::Error::other(
                                 "couldn't exec copy cmd; you may need to configure it manually",
                             )
                         } else {
                             e
                         }
                     })?;
-                    let mut stdin = child.stdin.take().ok_or_else(|| {
-                        io::Error::new(io::ErrorKind::Other, "couldn't get stdin of copy cmd")
-                    })?;
+                    let mut stdin = child
+                        .stdin
+                        .take()
+                        .ok_or_else(|| io::Error::other("couldn't get stdin of copy cmd"))?;
                     stdin.write_all(md.source().as_bytes())?;
                     Ok("copied to clipboard!".to_string())
                 })();
@@ -144,11 +324,16 @@ impl App {
             let mut v: ViewRef<LayoutView> = s
                 .find_name(NAME_FULL_LAYOUT)
                 .expect("bug: layout view should exist");
-            if let Some((qid, aid_opt)) = v.get_focused_ids() {
-                let question = arc2.questions.get(&qid).expect("bug: lost a question?!");
-                let url = aid_opt
-                    .map(|aid| arc2.site_map.answer_url(question, aid))
-                    .unwrap_or_else(|| arc2.site_map.question_url(question));
+            if let Some((qkey, akey_opt)) = v.get_focused_ids() {
+                let data = arc2.data.read().unwrap();
+                // N.B. the "loading more" row has no question
+                let Some(question) = data.questions.get(&qkey) else {
+                    return;
+                };
+                let site_map = &arc2.search.site_map;
+                let url = akey_opt
+                    .map(|(_, aid)| site_map.answer_url(question, aid))
+                    .unwrap_or_else(|| site_map.question_url(question));
                 let res = webbrowser::open(&url)
                     .map(|_| "opened stackexchange in the browser!".to_string());
                 temp_feedback_msg(s, res);
@@ -170,34 +355,422 @@ impl App {
         Ok(())
     }
 
-    pub fn question_selected_callback(&self, s: &mut Cursive, qid: u32) {
-        let q = self.questions.get(&qid).unwrap();
-        let body = &q.body;
+    /// Question list items ordered by the current sort
+    fn question_previews(&self) -> Vec<(StyledString, PostKey)> {
+        let sort = *self.sort.lock().unwrap();
+        let data = self.data.read().unwrap();
+        data.sorted_keys(sort)
+            .into_iter()
+            .map(|key| (preview_question(&data.questions[&key]), key))
+            .collect()
+    }
+
+    /// Title of the question list, with its sort and any sites still loading
+    fn question_list_title(&self) -> String {
+        let sort = *self.sort.lock().unwrap();
+        let title = format!("{} \u{00B7} sort: {sort}", Name::QuestionList);
+        let loading_sites = self.loading_sites.lock().unwrap();
+        if loading_sites.is_empty() {
+            title
+        } else {
+            format!(
+                "{title} \u{00B7} loading {}\u{2026}",
+                loading_sites.join(", ")
+            )
+        }
+    }
+
+    /// Refresh the question list with the current data, keeping the selection
+    fn refresh_question_list(&self, s: &mut Cursive) {
+        let cb = s
+            .call_on_name(NAME_QUESTION_LIST, |v: &mut ListView| {
+                v.set_title(self.question_list_title());
+                v.reset_keep_selection(self.question_previews())
+            })
+            .flatten();
+        if let Some(cb) = cb {
+            cb(s)
+        }
+    }
+
+    /// Stream the rest of the first page of results into the question list
+    /// as each site's questions arrive
+    fn stream_results(self: &Arc<Self>, s: &mut Cursive) {
+        let Some(StreamingSearch {
+            mut batches,
+            result,
+        }) = self.streaming.lock().unwrap().take()
+        else {
+            return;
+        };
+        let mut streamed: HashSet<PostKey> =
+            self.data.read().unwrap().ranking.iter().cloned().collect();
+        let app = self.clone();
+        let cb_sink = s.cb_sink().clone();
+        self.runtime.spawn(async move {
+            while let Some((site, qs)) = batches.recv().await {
+                streamed.extend(qs.iter().map(Question::key));
+                let qs = app.search.prepare_md(qs);
+                let app = app.clone();
+                cb_sink
+                    .send(Box::new(move |s| app.append_batch(s, &site, qs)))
+                    .expect("failed to send fn to cursive cb sink");
+            }
+            let res = match result.await.map_err(Error::from).and_then(|r| r) {
+                Ok(qs) => {
+                    let ranking = qs.iter().map(Question::key).collect::<Vec<_>>();
+                    let missing = qs
+                        .into_iter()
+                        .filter(|q| !streamed.contains(&q.key()))
+                        .collect();
+                    Ok((ranking, app.search.prepare_md(missing)))
+                }
+                Err(e) => Err(e),
+            };
+            cb_sink
+                .send(Box::new(move |s| app.finish_streaming(s, res)))
+                .expect("failed to send fn to cursive cb sink");
+        });
+    }
+
+    /// Append a site's streamed questions
+    fn append_batch(&self, s: &mut Cursive, site: &str, qs: Vec<Question<Markdown>>) {
+        self.loading_sites.lock().unwrap().retain(|s| s != site);
+        self.data.write().unwrap().extend(qs);
+        self.refresh_question_list(s);
+    }
+
+    /// Wrap up a streamed search, ordering questions by the final ranking
+    fn finish_streaming(
+        &self,
+        s: &mut Cursive,
+        res: Result<(Vec<PostKey>, Vec<Question<Markdown>>)>,
+    ) {
+        self.loading_sites.lock().unwrap().clear();
+        self.pager.lock().unwrap().loading = false;
+        let res = res.map(|(ranking, qs)| {
+            let mut data = self.data.write().unwrap();
+            data.extend(qs);
+            data.rerank(&ranking);
+        });
+        let hint = self.hint_text();
+        s.call_on_name(NAME_HINT_VIEW, |v: &mut TextView| v.set_content(hint));
+        self.refresh_question_list(s);
+        match res {
+            Ok(()) => temp_warnings_msg(s, self.search.take_warnings()),
+            Err(e) => temp_feedback_msg(s, Err(io::Error::other(e.to_string()))),
+        }
+    }
+
+    /// Fetch the next page of search results in the background, appending
+    /// them to the question list when they arrive
+    fn load_more(self: &Arc<Self>, s: &mut Cursive) {
+        let page = {
+            let mut pager = self.pager.lock().unwrap();
+            if pager.loading || pager.exhausted {
+                return;
+            }
+            pager.loading = true;
+            pager.next_page
+        };
+        s.call_on_name(NAME_QUESTION_LIST, |v: &mut ListView| {
+            v.add_item(loading_more(), LOADING_MORE_KEY)
+        });
+        let app = self.clone();
+        let cb_sink = s.cb_sink().clone();
+        self.runtime.spawn(async move {
+            let res = app.search.search_md_page(page).await;
+            cb_sink
+                .send(Box::new(move |s| app.append_page(s, res)))
+                .expect("failed to send fn to cursive cb sink");
+        });
+    }
+
+    /// Append a freshly loaded page of search results
+    fn append_page(&self, s: &mut Cursive, res: Result<Vec<Question<Markdown>>>) {
+        let res = res.map(|qs| self.data.write().unwrap().extend(qs));
+        if res.is_ok() {
+            // Later pages may come from a fallback engine
+            let hint = self.hint_text();
+            s.call_on_name(NAME_HINT_VIEW, |v: &mut TextView| v.set_content(hint));
+        }
+        {
+            let mut pager = self.pager.lock().unwrap();
+            pager.loading = false;
+            match res {
+                Ok(added) if added > 0 => pager.next_page += 1,
+                Ok(_) | Err(Error::NoResults) => pager.exhausted = true,
+                Err(_) => (),
+            }
+        }
+        self.refresh_question_list(s);
+        match res {
+            Ok(0) | Err(Error::NoResults) => {
+                temp_feedback_msg(s, Ok(String::from("no more results")))
+            }
+            Err(e) => temp_feedback_msg(s, Err(io::Error::other(e.to_string()))),
+            Ok(_) => temp_warnings_msg(s, self.search.take_warnings()),
+        }
+    }
+
+    /// Hint bar content for the current results
+    fn hint_text(&self) -> String {
+        let query = self.show_query.then_some(self.search.query.as_str());
+        hint(self.search.engine(), query)
+    }
+
+    /// Fetch the comments on a question and its answers in the background,
+    /// unless they're already loaded or on their way
+    fn load_comments(self: &Arc<Self>, s: &mut Cursive, key: &PostKey) {
+        let answer_ids = {
+            let mut data = self.data.write().unwrap();
+            if data.comments.contains_key(key) {
+                return;
+            }
+            // N.B. the "loading more" row has no question
+            let Some(q) = data.questions.get(key) else {
+                return;
+            };
+            let answer_ids = q.answers.iter().map(|a| a.id).collect::<Vec<_>>();
+            for post in post_keys(key, &answer_ids) {
+                data.comments.insert(post, CommentsState::Loading);
+            }
+            answer_ids
+        };
+        let app = self.clone();
+        let key = key.clone();
+        let cb_sink = s.cb_sink().clone();
+        self.runtime.spawn(async move {
+            let res = app.search.comments_md(&key.0, key.1, &answer_ids).await;
+            cb_sink
+                .send(Box::new(move |s| {
+                    app.attach_comments(s, &key, &answer_ids, res)
+                }))
+                .expect("failed to send fn to cursive cb sink");
+        });
+    }
+
+    /// Attach freshly loaded comments, refreshing the views if they show the
+    /// question. On failure, the next expansion tries again.
+    fn attach_comments(
+        &self,
+        s: &mut Cursive,
+        key: &PostKey,
+        answer_ids: &[Id],
+        res: Result<HashMap<Id, Vec<Comment<Markdown>>>>,
+    ) {
+        let res = res.map(|comments| {
+            self.data
+                .write()
+                .unwrap()
+                .attach_comments(key, answer_ids, comments)
+        });
+        if let Err(e) = res {
+            let mut data = self.data.write().unwrap();
+            for post in post_keys(key, answer_ids) {
+                data.comments.remove(&post);
+            }
+            drop(data);
+            temp_feedback_msg(s, Err(io::Error::other(e.to_string())));
+        }
+        if focused_ids(s).is_some_and(|(qkey, _)| qkey == *key) {
+            self.refresh_post_views(s);
+        }
+    }
+
+    /// Re-render the focused question and answer, e.g. after toggling comments
+    fn refresh_post_views(&self, s: &mut Cursive) {
+        let Some((qkey, akey_opt)) = focused_ids(s) else {
+            return;
+        };
+        let data = self.data.read().unwrap();
+        if data.questions.contains_key(&qkey) {
+            let content = self.question_content(&data, &qkey);
+            s.call_on_name(NAME_QUESTION_VIEW, |v: &mut MdView| v.set_content(&content));
+        }
+        if let Some(akey) = akey_opt.filter(|akey| data.answers.contains_key(akey)) {
+            let content = self.answer_content(&data, &akey);
+            s.call_on_name(NAME_ANSWER_VIEW, |v: &mut MdView| v.set_content(&content));
+        }
+    }
+
+    /// Question metadata and body, followed by its comments if expanded
+    fn question_content(&self, data: &AppData, key: &PostKey) -> Markdown {
+        let q = &data.questions[key];
+        let mut content = question_header(q);
+        content.append(q.body.clone());
+        with_comments(
+            content,
+            &q.comments,
+            data.comments.get(key).copied(),
+            self.show_comments.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Answer metadata and body, followed by its comments if expanded
+    fn answer_content(&self, data: &AppData, key: &PostKey) -> Markdown {
+        let a = &data.answers[key];
+        let mut content = answer_header(a);
+        content.append(a.body.clone());
+        with_comments(
+            content,
+            &a.comments,
+            data.comments.get(key).copied(),
+            self.show_comments.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn question_selected_callback(self: &Arc<Self>, s: &mut Cursive, key: &PostKey) {
+        if self.show_comments.load(Ordering::Relaxed) {
+            self.load_comments(s, key);
+        }
+        let data = self.data.read().unwrap();
+        // N.B. the "loading more" row has no question
+        let Some(q) = data.questions.get(key) else {
+            return;
+        };
+        let content = self.question_content(&data, key);
         let XY { x, y: _y } = s.screen_size();
         // Update question view
         s.call_on_name(NAME_QUESTION_VIEW, |v: &mut MdView| {
-            v.set_content(body);
+            v.set_content(&content);
         })
         .expect("Panic: setting question view content failed");
-        // Update answer list view
+        // Update answer list view, selecting the answer the search result
+        // linked to, if any
+        let linked = q.linked_answer.map(|id| (key.0.clone(), id));
         let cb = s
             .call_on_name(NAME_ANSWER_LIST, |v: &mut ListView| {
-                v.reset_with_all(q.answers.iter().map(|a| (preview_answer(x, a), a.id)))
+                let cb = v.reset_with_all(q.answers.iter().map(|a| {
+                    let is_linked = q.linked_answer == Some(a.id);
+                    (preview_answer(x, a, is_linked), (key.0.clone(), a.id))
+                }));
+                linked.and_then(|key| v.select_value(&key)).unwrap_or(cb)
             })
             .expect("Panic: setting answer list content failed");
+        drop(data);
         cb(s)
     }
 }
 
+impl AppData {
+    /// Add questions to the end of the ranking, skipping any already present.
+    /// Returns the number of questions added.
+    fn extend(&mut self, qs: Vec<Question<Markdown>>) -> usize {
+        let mut added = 0;
+        for q in qs {
+            let key = q.key();
+            if self.questions.contains_key(&key) {
+                continue;
+            }
+            self.ranking.push(key.clone());
+            self.answers
+                .extend(q.answers.iter().map(|a| ((key.0.clone(), a.id), a.clone())));
+            self.questions.insert(key, q);
+            added += 1;
+        }
+        added
+    }
+
+    /// Attach the comments loaded for a question and its answers
+    fn attach_comments(
+        &mut self,
+        key: &PostKey,
+        answer_ids: &[Id],
+        mut comments: HashMap<Id, Vec<Comment<Markdown>>>,
+    ) {
+        for post in post_keys(key, answer_ids) {
+            self.comments.insert(post, CommentsState::Loaded);
+        }
+        if let Some(q) = self.questions.get_mut(key) {
+            q.comments = comments.remove(&key.1).unwrap_or_default();
+            for a in q.answers.iter_mut() {
+                a.comments = comments.get(&a.id).cloned().unwrap_or_default();
+            }
+        }
+        for id in answer_ids {
+            if let Some(a) = self.answers.get_mut(&(key.0.clone(), *id)) {
+                a.comments = comments.remove(id).unwrap_or_default();
+            }
+        }
+    }
+
+    /// Reorder the ranking to follow `keys`, e.g. the final ranking of results
+    /// that streamed in out of order. Questions missing from `keys` keep their
+    /// relative order, after the rest.
+    fn rerank(&mut self, keys: &[PostKey]) {
+        let position: HashMap<&PostKey, usize> =
+            keys.iter().enumerate().map(|(ix, key)| (key, ix)).collect();
+        self.ranking
+            .sort_by_key(|key| position.get(key).copied().unwrap_or(usize::MAX));
+    }
+
+    /// Questions ordered by the given sort
+    fn sorted_keys(&self, sort: QuestionSort) -> Vec<PostKey> {
+        let mut ids = self.ranking.clone();
+        // N.B. stable sorts, so ties fall back to engine ranking
+        match sort {
+            QuestionSort::Rank => (),
+            QuestionSort::Score => ids.sort_by_key(|id| -self.questions[id].score),
+            QuestionSort::Answers => {
+                ids.sort_by_key(|id| std::cmp::Reverse(self.questions[id].answers.len()))
+            }
+            QuestionSort::Site => {
+                ids.sort_by(|a, b| self.questions[a].site.cmp(&self.questions[b].site))
+            }
+        }
+        ids
+    }
+}
+
+fn loading_more() -> StyledString {
+    SpannedString::styled(
+        "loading more\u{2026}",
+        Style::merge(&[
+            Style::from(Color::Light(BaseColor::Yellow)),
+            Style::from(Effect::Italic),
+        ]),
+    )
+}
+
+/// Hint bar content, including the search engine that produced the results
+/// and the query, if shown
+fn hint(engine: Option<SearchEngine>, query: Option<&str>) -> String {
+    let mut hint = String::from("? help \u{00B7} q quit");
+    if let Some(engine) = engine {
+        write!(hint, " \u{00B7} results via {engine}").ok();
+    }
+    if let Some(query) = query {
+        write!(hint, " \u{00B7} searched `{query}`").ok();
+    }
+    hint
+}
+
 fn preview_question(q: &Question<Markdown>) -> StyledString {
     let mut preview = pretty_score(q.score);
+    preview.append(pretty_year(q.last_edit_date.or(q.creation_date)));
+    preview.append(pretty_closed(q.closed_reason.as_deref()));
     preview.append_plain(&q.title);
+    // Full text search matches show the snippet that matched
+    if let Some(excerpt) = &q.excerpt {
+        preview.append_plain(" \u{2014} "); // " — "
+        preview.append(excerpt.clone());
+    }
     preview
 }
 
-fn preview_answer(screen_width: usize, a: &Answer<Markdown>) -> StyledString {
+fn preview_answer(screen_width: usize, a: &Answer<Markdown>, linked: bool) -> StyledString {
     let md = markdown::preview(screen_width, &a.body);
     let mut preview = pretty_score(a.score);
+    if linked {
+        preview.append_styled(
+            "\u{2192} ", // "→ "
+            Style::merge(&[
+                Style::from(Color::Light(BaseColor::Magenta)),
+                Style::from(Effect::Bold),
+            ]),
+        );
+    }
     if a.is_accepted {
         preview.append_styled(
             "\u{2713} ", // "✔ "
@@ -207,10 +780,173 @@ fn preview_answer(screen_width: usize, a: &Answer<Markdown>) -> StyledString {
             ]),
         );
     }
+    preview.append(pretty_year(a.last_edit_date.or(a.creation_date)));
     preview.append(md);
     preview
 }
 
+/// Header line of tags, author, dates, views, and closed status
+fn question_header(q: &Question<Markdown>) -> StyledString {
+    let mut items = Vec::new();
+    if !q.tags.is_empty() {
+        let tags = q.tags.iter().map(|t| format!("[{t}]")).collect::<Vec<_>>();
+        items.push(SpannedString::styled(
+            tags.join(" "),
+            Color::Light(BaseColor::Cyan),
+        ));
+    }
+    items.extend(post_dates(
+        "asked",
+        q.creation_date,
+        q.last_edit_date,
+        q.owner.as_ref(),
+    ));
+    if let Some(views) = q.view_count {
+        items.push(StyledString::plain(format!("{views} views")));
+    }
+    if let Some(reason) = &q.closed_reason {
+        items.push(SpannedString::styled(
+            format!("closed: {reason}"),
+            Color::Light(BaseColor::Red),
+        ));
+    }
+    if !q.engines.is_empty() {
+        let engines = q.engines.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        items.push(SpannedString::styled(
+            format!("via {}", engines.join(", ")),
+            Color::Light(BaseColor::Magenta),
+        ));
+    }
+    header_line(items)
+}
+
+/// Header line of author and dates
+fn answer_header(a: &Answer<Markdown>) -> StyledString {
+    let items = post_dates(
+        "answered",
+        a.creation_date,
+        a.last_edit_date,
+        a.owner.as_ref(),
+    );
+    header_line(items)
+}
+
+fn post_dates(
+    verb: &str,
+    created: Option<i64>,
+    edited: Option<i64>,
+    owner: Option<&User>,
+) -> Vec<StyledString> {
+    let mut items = Vec::new();
+    if let Some(created) = created {
+        let mut item = StyledString::plain(format!("{verb} {}", utils::format_date(created)));
+        if let Some(name) = owner.and_then(|u| u.display_name.as_deref()) {
+            item.append_plain(format!(" by {name}"));
+            if let Some(rep) = owner.and_then(|u| u.reputation) {
+                item.append_plain(format!(" ({rep})"));
+            }
+        }
+        items.push(item);
+    }
+    if let Some(edited) = edited {
+        items.push(StyledString::plain(format!(
+            "edited {}",
+            utils::format_date(edited)
+        )));
+    }
+    items
+}
+
+fn header_line(items: Vec<StyledString>) -> StyledString {
+    let mut header = StyledString::new();
+    if items.is_empty() {
+        return header;
+    }
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            header.append_styled(" \u{00B7} ", Effect::Dim);
+        }
+        header.append(item);
+    }
+    header.append_plain("\n\n");
+    header
+}
+
+/// The question list's focused question and answer, if any
+fn focused_ids(s: &mut Cursive) -> Option<(PostKey, Option<PostKey>)> {
+    let mut v: ViewRef<LayoutView> = s
+        .find_name(NAME_FULL_LAYOUT)
+        .expect("bug: layout view should exist");
+    v.get_focused_ids()
+}
+
+/// Keys of a question and its answers
+fn post_keys<'a>(key: &'a PostKey, answer_ids: &'a [Id]) -> impl Iterator<Item = PostKey> + 'a {
+    std::iter::once(key.clone()).chain(answer_ids.iter().map(move |id| (key.0.clone(), *id)))
+}
+
+fn with_comments(
+    mut content: StyledString,
+    comments: &[Comment<Markdown>],
+    state: Option<CommentsState>,
+    expanded: bool,
+) -> Markdown {
+    let n = comments.len();
+    let plural = if n == 1 { "" } else { "s" };
+    // Comments are only fetched once expanded, so until then their count
+    // isn't known
+    let hint = match state {
+        Some(CommentsState::Loaded) if n == 0 => return content,
+        Some(CommentsState::Loaded) if expanded => None,
+        Some(CommentsState::Loaded) => Some(format!("[c] show {n} comment{plural}")),
+        Some(CommentsState::Loading) if expanded => Some(String::from("loading comments\u{2026}")),
+        _ => Some(String::from("[c] show comments")),
+    };
+    content.append_plain("\n\n");
+    if let Some(hint) = hint {
+        content.append_styled(hint, Style::from(Effect::Italic));
+        return content;
+    }
+    content.append_styled(format!("{n} comment{plural}"), Style::from(Effect::Bold));
+    for c in comments {
+        let author = c
+            .owner
+            .as_ref()
+            .and_then(|u| u.display_name.as_deref())
+            .unwrap_or("unknown");
+        content.append_plain("\n\n");
+        content.append(pretty_score(c.score));
+        content.append(c.body.clone());
+        content.append_styled(
+            format!(
+                " \u{2013} {author}, {}",
+                utils::format_date(c.creation_date)
+            ),
+            Style::from(Effect::Italic),
+        );
+    }
+    content
+}
+
+fn pretty_year(date: Option<i64>) -> StyledString {
+    match date {
+        Some(date) => {
+            SpannedString::styled(format!("{} ", &utils::format_date(date)[..4]), Effect::Dim)
+        }
+        None => StyledString::new(),
+    }
+}
+
+fn pretty_closed(reason: Option<&str>) -> StyledString {
+    match reason {
+        Some(reason) if reason.eq_ignore_ascii_case("duplicate") => {
+            SpannedString::styled("[duplicate] ", Color::Light(BaseColor::Red))
+        }
+        Some(_) => SpannedString::styled("[closed] ", Color::Light(BaseColor::Red)),
+        None => StyledString::new(),
+    }
+}
+
 fn pretty_score(score: i32) -> StyledString {
     let color = if score > 0 {
         Color::Light(BaseColor::Green)
@@ -242,6 +978,8 @@ pub fn help() -> Dialog {
 ## Misc
 **o**:              Open current q/a in the browser
 **y**:              Copy current q/a to the clipboard
+**s**:              Cycle question sort (rank, score, answers, site)
+**c**:              Expand / collapse comments
 **q, ZZ, Ctrl<c>**: Exit
 **Ctrl<r>**:        Reload theme
 **?**:              Toggle this help menu
@@ -256,15 +994,34 @@ pub fn help() -> Dialog {
 }
 
 pub fn temp_feedback_msg(siv: &mut Cursive, msg: io::Result<String>) {
-    // TODO semaphore to close existing msg before displaying new one?
     let style = if msg.is_ok() {
         Color::Light(BaseColor::Green)
     } else {
         Color::Light(BaseColor::Red)
     };
     let content = msg.unwrap_or_else(|e| format!("error: {e}"));
-    let styled_content = SpannedString::styled(content, style);
-    let layer = Dialog::around(TextView::new(styled_content));
+    temp_msg(siv, SpannedString::styled(content, style));
+}
+
+/// Show non-fatal search problems, if any
+fn temp_warnings_msg(siv: &mut Cursive, warnings: Vec<String>) {
+    if warnings.is_empty() {
+        return;
+    }
+    let content = warnings
+        .into_iter()
+        .map(|w| format!("warning: {w}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    temp_msg(
+        siv,
+        SpannedString::styled(content, Color::Light(BaseColor::Yellow)),
+    );
+}
+
+fn temp_msg(siv: &mut Cursive, content: StyledString) {
+    // TODO semaphore to close existing msg before displaying new one?
+    let layer = Dialog::around(TextView::new(content));
     let temp = TempView::new(layer, siv.cb_sink().clone());
     siv.add_layer(temp);
 }
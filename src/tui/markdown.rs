@@ -11,6 +11,7 @@ use cursive::theme::{Effect, PaletteColor, Style};
 use cursive::utils::markup::{StyledIndexedSpan, StyledString};
 use cursive::utils::span::{IndexedCow, IndexedSpan};
 use pulldown_cmark::{self, CowStr, Event, HeadingLevel, Options, Tag};
+use scraper::{ElementRef, Html};
 
 pub type Markdown = StyledString;
 
@@ -34,6 +35,43 @@ pub fn preprocess(input: String) -> String {
         .replace("</kbd>", "]**")
 }
 
+/// Convert a StackExchange search excerpt to markdown on a single line.
+/// Excerpts are plain text, except for the matching terms wrapped in
+/// `<span class="highlight">`, which are emphasized.
+pub fn from_excerpt(input: &str) -> String {
+    let fragment = Html::parse_fragment(input);
+    let mut output = String::new();
+    for node in fragment.root_element().children() {
+        if let Some(text) = node.value().as_text() {
+            output.push_str(&escape(text));
+        } else if let Some(element) = ElementRef::wrap(node) {
+            let text = escape(&element.text().collect::<String>());
+            if element.value().classes().any(|c| c == "highlight") && !text.trim().is_empty() {
+                output.push_str(&format!("**{}**", text.trim()));
+            } else {
+                output.push_str(&text);
+            }
+        }
+    }
+    output.trim().to_string()
+}
+
+/// Escape markdown syntax in plain text, and flatten newlines
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// Preview markdown of the given length
 /// Currently removes any color (i.e. code highlighting) to avoid
 /// the jarring issue of a fragmented highlight style on focused items.
@@ -132,10 +170,7 @@ impl<'a, 'b> Iterator for Parser<'a, 'b> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let next = match self.parser.next() {
-                None => return None,
-                Some(event) => event,
-            };
+            let next = self.parser.next()?;
 
             match next {
                 // Add styles to the stack
@@ -229,6 +264,28 @@ mod tests {
     use super::*;
     use cursive::utils::span::Span;
 
+    #[test]
+    fn test_from_excerpt() {
+        let input = "How do I <span class=\"highlight\">exit</span> &quot;*vim*&quot;?\n\
+            Type <span class=\"highlight\">:q</span> &amp; hit enter";
+        assert_eq!(
+            from_excerpt(input),
+            r#"How do I **exit** "\*vim\*"? Type **:q** & hit enter"#
+        );
+
+        let parsed = parse(from_excerpt(input));
+        assert_eq!(
+            parsed.source(),
+            r#"How do I **exit** "\*vim\*"? Type **:q** & hit enter"#
+        );
+        let bold: Vec<_> = parsed
+            .spans()
+            .filter(|span| *span.attr == Style::from(Effect::Bold))
+            .map(|span| span.content)
+            .collect();
+        assert_eq!(bold, vec!["exit", ":q"]);
+    }
+
     #[test]
     fn test_basic_styles() {
         let input = r"
@@ -1,4 +1,5 @@
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, thread};
 use std::{
@@ -6,7 +7,7 @@ use std::{
     marker::{Send, Sync},
 };
 
-use cursive::event::{Callback, Event, EventResult, Key};
+use cursive::event::{Callback, Event, EventResult, Key, MouseEvent};
 use cursive::traits::{Finder, Nameable, Resizable, Scrollable};
 use cursive::utils::markup::StyledString;
 use cursive::view::{CannotFocus, Margins, SizeConstraint, View, ViewWrapper};
@@ -17,6 +18,7 @@ use cursive::views::{
 use cursive::{CbSink, Cursive, Vec2, XY};
 
 use super::markdown::Markdown;
+use crate::stackexchange::PostKey;
 
 pub const NAME_QUESTION_LIST: &str = "question_list";
 pub const NAME_ANSWER_LIST: &str = "answer_list";
@@ -76,14 +78,18 @@ trait Hide {
 
 // TODO Copy select_view to to allow overriding selected style => reverse video
 pub type ListView =
-    ListViewT<HideableView<ResizedView<Panel<ScrollView<NamedView<SelectView<u32>>>>>>>;
+    ListViewT<HideableView<ResizedView<Panel<ScrollView<NamedView<SelectView<PostKey>>>>>>>;
 
 pub struct ListViewT<T: View> {
     inner_name: String,
     view: T,
     force_take_focus: bool,
+    /// Called when the last item is selected, or when scrolling down past it
+    on_scroll_end: Option<SharedCallback>,
 }
 
+type SharedCallback = Arc<dyn Fn(&mut Cursive) + Sync + Send>;
+
 impl<T: View> ViewWrapper for ListViewT<T> {
     cursive::wrap_impl!(self.view: T);
 
@@ -109,7 +115,35 @@ impl<T: View> ViewWrapper for ListViewT<T> {
                 | Event::Key(Key::Up)
         );
 
-        match self.view.on_event(event) {
+        let moves_down = matches!(
+            event,
+            Event::Key(Key::Down)
+                | Event::Key(Key::PageDown)
+                | Event::Key(Key::End)
+                | Event::Mouse {
+                    event: MouseEvent::WheelDown,
+                    ..
+                }
+        );
+
+        let mut result = self.view.on_event(event);
+        if let (true, Some(cb)) = (moves_down, &self.on_scroll_end) {
+            // N.B. the mouse wheel scrolls without moving the selection, and
+            // is ignored once there is nothing left to scroll
+            let at_end = matches!(result, EventResult::Ignored)
+                || self
+                    .view
+                    .call_on_name(&self.inner_name, |sv: &mut SelectView<PostKey>| {
+                        sv.selected_id().map(|ix| ix + 1) == Some(sv.len())
+                    })
+                    .unwrap_or(false);
+            if at_end {
+                let cb = cb.clone();
+                result = result.and(EventResult::with_cb(move |s| cb(s)));
+            }
+        }
+
+        match result {
             EventResult::Ignored if should_consume => EventResult::Consumed(None),
             event_result => event_result,
         }
@@ -119,7 +153,7 @@ impl<T: View> ViewWrapper for ListViewT<T> {
 impl ListView {
     pub fn new<F>(name: Name, on_select: F) -> NamedView<Self>
     where
-        F: Fn(&mut Cursive, &u32) + 'static + Sync + Send,
+        F: Fn(&mut Cursive, &PostKey) + 'static + Sync + Send,
     {
         ListView::make_new::<StyledString, Vec<_>, _>(name, None, on_select)
     }
@@ -127,8 +161,8 @@ impl ListView {
     pub fn new_with_items<S, I, F>(name: Name, items: I, on_select: F) -> NamedView<Self>
     where
         S: Into<StyledString>,
-        I: IntoIterator<Item = (S, u32)>,
-        F: Fn(&mut Cursive, &u32) + 'static + Sync + Send,
+        I: IntoIterator<Item = (S, PostKey)>,
+        F: Fn(&mut Cursive, &PostKey) + 'static + Sync + Send,
     {
         ListView::make_new(name, Some(items), on_select)
     }
@@ -136,8 +170,8 @@ impl ListView {
     fn make_new<S, I, F>(name: Name, items: Option<I>, on_select: F) -> NamedView<Self>
     where
         S: Into<StyledString>,
-        I: IntoIterator<Item = (S, u32)>,
-        F: Fn(&mut Cursive, &u32) + 'static + Sync + Send,
+        I: IntoIterator<Item = (S, PostKey)>,
+        F: Fn(&mut Cursive, &PostKey) + 'static + Sync + Send,
     {
         let inner_name = name.to_string() + "_inner";
         let mut view = SelectView::new().on_select(on_select);
@@ -153,19 +187,20 @@ impl ListView {
             view,
             inner_name,
             force_take_focus: false,
+            on_scroll_end: None,
         };
 
         view.with_name(name)
     }
 
-    pub fn get_current_selection(&mut self) -> Option<u32> {
-        self.call_on_inner(|sv| sv.selection().as_deref().copied())
+    pub fn get_current_selection(&mut self) -> Option<PostKey> {
+        self.call_on_inner(|sv| sv.selection().as_deref().cloned())
     }
 
     pub fn reset_with_all<S, I>(&mut self, iter: I) -> Callback
     where
         S: Into<StyledString>,
-        I: IntoIterator<Item = (S, u32)>,
+        I: IntoIterator<Item = (S, PostKey)>,
     {
         self.call_on_inner(|s| {
             s.clear();
@@ -174,13 +209,59 @@ impl ListView {
         })
     }
 
+    /// Replace all items, keeping the current selection if it is still present.
+    /// Otherwise the selection stays at the same index, and the returned callback
+    /// should be run to handle the new selection.
+    pub fn reset_keep_selection<S, I>(&mut self, iter: I) -> Option<Callback>
+    where
+        S: Into<StyledString>,
+        I: IntoIterator<Item = (S, PostKey)>,
+    {
+        self.call_on_inner(|s| {
+            let selection = s.selection().as_deref().cloned();
+            let ix = s.selected_id().unwrap_or(0);
+            s.clear();
+            s.add_all(iter);
+            match selection.and_then(|v| s.iter().position(|(_, item)| *item == v)) {
+                Some(new_ix) => {
+                    s.set_selection(new_ix);
+                    None
+                }
+                None => Some(s.set_selection(ix.min(s.len().saturating_sub(1)))),
+            }
+        })
+    }
+
+    pub fn add_item<S: Into<StyledString>>(&mut self, label: S, value: PostKey) {
+        self.call_on_inner(|s| s.add_item(label, value))
+    }
+
+    pub fn set_on_scroll_end<F>(&mut self, cb: F)
+    where
+        F: Fn(&mut Cursive) + 'static + Sync + Send,
+    {
+        self.on_scroll_end = Some(Arc::new(cb));
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.view.get_inner_mut().get_inner_mut().set_title(title);
+    }
+
     pub fn select(&mut self, i: usize) -> Callback {
         self.call_on_inner(|sv| sv.set_selection(i))
     }
 
+    /// Select the item with the given value, if present
+    pub fn select_value(&mut self, value: &PostKey) -> Option<Callback> {
+        self.call_on_inner(|sv| {
+            let i = sv.iter().position(|(_, v)| v == value)?;
+            Some(sv.set_selection(i))
+        })
+    }
+
     fn call_on_inner<F, R>(&mut self, cb: F) -> R
     where
-        F: FnOnce(&mut SelectView<u32>) -> R,
+        F: FnOnce(&mut SelectView<PostKey>) -> R,
     {
         self.view
             .call_on_name(&self.inner_name, cb)
@@ -407,7 +488,7 @@ impl LayoutView {
     }
 
     // There may be no questions and there may be no answers? There should be answers but w/e
-    pub fn get_focused_ids(&mut self) -> Option<(u32, Option<u32>)> {
+    pub fn get_focused_ids(&mut self) -> Option<(PostKey, Option<PostKey>)> {
         let curr_question = self
             .view
             .call_on_name(NAME_QUESTION_LIST, |v: &mut ListView| {
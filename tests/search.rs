@@ -0,0 +1,707 @@
+//! End-to-end tests running searches against a local fixture server, standing
+//! in for the StackExchange API and the search engines.
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use so::config::{Config, CustomEngine, SearchEngine, SearchFilters, SearchSort};
+use so::error::Error;
+use so::stackexchange::{Question, Search};
+
+/// Questions known to the fixture API, as (site, id, score)
+const QUESTIONS: &[(&str, u32, i32)] = &[
+    ("stackoverflow", 11828270, 4000),
+    ("stackoverflow", 25919461, 100),
+    ("askubuntu", 24406, 5000),
+    // Same id as a stackoverflow question
+    ("superuser", 11828270, 50),
+];
+
+/// Question fixture, asked `id` seconds into 1970; 25919461 is closed, with no
+/// accepted answer
+fn question(site: &str, id: u32, score: i32) -> Value {
+    let closed = id == 25919461;
+    json!({
+        "question_id": id,
+        "score": score,
+        "title": format!("{site} question {id}"),
+        "body_markdown": "How do I exit vim?",
+        "creation_date": id,
+        "closed_reason": if closed { Some("duplicate") } else { None },
+        "answers": [{
+            "answer_id": id + 1,
+            "score": score / 2,
+            "body_markdown": "`:q`",
+            "is_accepted": !closed
+        }, {
+            "answer_id": id + 2,
+            "score": score / 4,
+            "body_markdown": "`ZQ`",
+            "is_accepted": false
+        }]
+    })
+}
+
+/// Fixture response status for a request target; Google blocks the `captcha`
+/// and `ratelimited` sites
+fn status(target: &str) -> &'static str {
+    match target {
+        t if !t.starts_with("/google/") => "200 OK",
+        t if t.contains("captcha") => "429 Too Many Requests",
+        t if t.contains("ratelimited") => "503 Service Unavailable",
+        _ => "200 OK",
+    }
+}
+
+/// Fixture response body for a request target, e.g. `/api/2.2/questions/1?site=x`
+fn respond(target: &str) -> Option<String> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let site = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("site="))
+        .unwrap_or_default();
+    if site == "broken" {
+        return Some(
+            json!({ "error_id": 400, "error_name": "bad_parameter", "error_message": "site" })
+                .to_string(),
+        );
+    }
+    // All questions are tagged `vim`
+    let tagged = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("tagged="))
+        .unwrap_or("vim");
+    // Searching for "low quota" runs down the API quota
+    let quota = if query.contains("low+quota") {
+        42
+    } else {
+        9000
+    };
+    let questions = |filter: &dyn Fn(u32) -> bool| {
+        let items: Vec<Value> = QUESTIONS
+            .iter()
+            .filter(|(s, id, _)| *s == site && filter(*id))
+            .map(|(s, id, score)| question(s, *id, *score))
+            .collect();
+        json!({ "items": items, "has_more": false, "quota_remaining": quota }).to_string()
+    };
+    let body = match path {
+        "/google/search" if query.contains("captcha") => {
+            include_str!("../test/google/captcha.html").to_string()
+        }
+        "/google/search" if query.contains("ratelimited") => String::new(),
+        "/google/search" if query.contains("answer+links") => {
+            include_str!("../test/google/answer-links.html").to_string()
+        }
+        "/google/search" if query.contains("superuser") => {
+            include_str!("../test/google/colliding-ids.html").to_string()
+        }
+        "/google/search" if query.contains("%5Bemacs%5D") => {
+            include_str!("../test/google/no-results.html").to_string()
+        }
+        "/google/search" => include_str!("../test/google/exit-vim.html").to_string(),
+        "/duckduckgo" => include_str!("../test/duckduckgo/bad-user-agent.html").to_string(),
+        "/bing/search" => include_str!("../test/bing/exit-vim.html").to_string(),
+        "/brave/search" => include_str!("../test/brave/captcha.html").to_string(),
+        "/startpage/search" => include_str!("../test/startpage/exit-vim.html").to_string(),
+        "/searxng/search" => include_str!("../test/searxng/exit-vim.json").to_string(),
+        "/api/2.2/filters/create" => json!({ "items": [{ "filter": "fixture" }] }).to_string(),
+        "/api/2.2/search/advanced" => questions(&|_| tagged == "vim"),
+        "/api/2.2/similar" => questions(&|_| true),
+        "/api/2.2/search/excerpts" => {
+            // Matches the site's questions in reverse, each through an answer
+            // as well as the question itself
+            let items: Vec<Value> = QUESTIONS
+                .iter()
+                .rev()
+                .filter(|(s, _, _)| *s == site)
+                .flat_map(|(_, id, _)| {
+                    [
+                        json!({
+                            "item_type": "answer",
+                            "question_id": id,
+                            "answer_id": id + 2,
+                            "excerpt": "Type <span class=\"highlight\">ZQ</span> to &quot;exit&quot;"
+                        }),
+                        json!({
+                            "item_type": "question",
+                            "question_id": id,
+                            "excerpt": "How do I <span class=\"highlight\">exit</span> vim?"
+                        }),
+                    ]
+                })
+                .collect();
+            json!({ "items": items, "has_more": false }).to_string()
+        }
+        p if p.ends_with("/comments") => {
+            // One comment on every post
+            let ids = p.trim_end_matches("/comments").rsplit('/').next().unwrap();
+            let items: Vec<Value> = ids
+                .split(';')
+                .filter_map(|id| id.parse::<u32>().ok())
+                .map(|id| {
+                    json!({
+                        "comment_id": id,
+                        "post_id": id,
+                        "score": 1,
+                        "body_markdown": "Or `:wq`",
+                        "creation_date": id
+                    })
+                })
+                .collect();
+            json!({ "items": items, "has_more": false }).to_string()
+        }
+        p if p.starts_with("/api/2.2/answers/") => {
+            let ids = &p["/api/2.2/answers/".len()..];
+            let items: Vec<Value> = QUESTIONS
+                .iter()
+                .filter(|(s, _, _)| *s == site)
+                .flat_map(|(_, id, _)| [(id + 1, id), (id + 2, id)])
+                .filter(|(answer_id, _)| ids.split(';').any(|i| i == answer_id.to_string()))
+                .map(|(answer_id, id)| json!({ "answer_id": answer_id, "question_id": id }))
+                .collect();
+            json!({ "items": items, "has_more": false }).to_string()
+        }
+        p if p.starts_with("/api/2.2/questions/") => {
+            let ids = &p["/api/2.2/questions/".len()..];
+            questions(&|id| ids.split(';').any(|i| i == id.to_string()))
+        }
+        _ => return None,
+    };
+    Some(body)
+}
+
+/// Serve fixtures on a random local port, returning its URL
+async fn fixture_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let target = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match respond(target) {
+                    Some(body) => (status(target), body),
+                    None => ("404 Not Found", String::new()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    format!("http://{addr}")
+}
+
+/// Search for `query` configured against a fresh fixture server
+async fn fixture_search_for(
+    engines: &[SearchEngine],
+    sites: &[&str],
+    query: &str,
+) -> so::error::Result<Search> {
+    let server = fixture_server().await;
+    let custom = CustomEngine {
+        name: String::from("custom"),
+        url: format!("{server}/startpage/search?query={{sites}}%20{{query}}"),
+        selector: String::from("a.result-link"),
+        href_attribute: None,
+        redirect_param: None,
+        blocked: None,
+    };
+    let config = Config {
+        api_url: Some(format!("{server}/api")),
+        google_url: Some(format!("{server}/google/search")),
+        duckduckgo_url: Some(format!("{server}/duckduckgo")),
+        bing_url: Some(format!("{server}/bing/search")),
+        brave_url: Some(format!("{server}/brave/search")),
+        searxng_url: Some(format!("{server}/searxng")),
+        engines: vec![custom],
+        search_engine: engines.to_vec(),
+        sites: sites.iter().map(|s| s.to_string()).collect(),
+        limit: 3,
+        retries: 0,
+        no_proxy: vec![String::from("127.0.0.1")],
+        ..Config::default()
+    };
+    let site_map = sites
+        .iter()
+        .map(|s| (s.to_string(), format!("{s}.com")))
+        .collect();
+    Search::new(config, Arc::new(site_map), query.to_string())
+}
+
+async fn search_questions(
+    engines: &[SearchEngine],
+    sites: &[&str],
+) -> so::error::Result<(Vec<Question<String>>, Search)> {
+    let search = fixture_search_for(engines, sites, "exit vim").await?;
+    let qs = search.search().await?;
+    assert!(qs.iter().all(|q| !q.answers.is_empty()));
+    Ok((qs, search))
+}
+
+async fn search(
+    engines: &[SearchEngine],
+    sites: &[&str],
+) -> so::error::Result<(Vec<(String, u32)>, Option<SearchEngine>)> {
+    let (qs, search) = search_questions(engines, sites).await?;
+    Ok((keys(qs), search.engine()))
+}
+
+fn keys(qs: Vec<Question<String>>) -> Vec<(String, u32)> {
+    qs.into_iter()
+        .map(|q| (q.site.unwrap_or_default(), q.id))
+        .collect()
+}
+
+fn ids(expected: &[(&str, u32)]) -> Vec<(String, u32)> {
+    expected
+        .iter()
+        .map(|(s, id)| (s.to_string(), *id))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_google_search() {
+    let (results, engine) = search(&[SearchEngine::Google], &["stackoverflow", "askubuntu"])
+        .await
+        .unwrap();
+    assert_eq!(engine, Some(SearchEngine::Google));
+    // Ranked as in the search results, regardless of score
+    assert_eq!(
+        results,
+        ids(&[
+            ("stackoverflow", 11828270),
+            ("stackoverflow", 25919461),
+            ("askubuntu", 24406)
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_stackexchange_search() {
+    let (results, _) = search(
+        &[SearchEngine::StackExchange],
+        &["stackoverflow", "askubuntu"],
+    )
+    .await
+    .unwrap();
+    // Sorted by score across sites
+    assert_eq!(
+        results,
+        ids(&[
+            ("askubuntu", 24406),
+            ("stackoverflow", 11828270),
+            ("stackoverflow", 25919461)
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_stackexchange_similar() {
+    let (results, _) = search(
+        &[SearchEngine::StackExchangeSimilar],
+        &["stackoverflow", "askubuntu"],
+    )
+    .await
+    .unwrap();
+    // Interleaved by rank across sites, regardless of score
+    assert_eq!(
+        results,
+        ids(&[
+            ("stackoverflow", 11828270),
+            ("askubuntu", 24406),
+            ("stackoverflow", 25919461)
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_stackexchange_excerpts() {
+    let (qs, _) = search_questions(&[SearchEngine::StackExchangeExcerpts], &["stackoverflow"])
+        .await
+        .unwrap();
+    // Ranked as in the excerpts, each question once
+    let results: Vec<_> = qs.iter().map(|q| q.id).collect();
+    assert_eq!(results, vec![25919461, 11828270]);
+    // The best ranked match wins, linking the answer it came from
+    for q in qs {
+        assert_eq!(q.linked_answer, Some(q.id + 2));
+        assert_eq!(q.excerpt.as_deref(), Some(r#"Type **ZQ** to "exit""#));
+    }
+
+    let results = search(&[SearchEngine::StackExchangeExcerpts], &["serverfault"]).await;
+    assert!(matches!(results, Err(Error::NoResults)));
+}
+
+#[tokio::test]
+async fn test_tags() {
+    for engine in [SearchEngine::StackExchange, SearchEngine::Google] {
+        let mut search = fixture_search_for(&[engine], &["stackoverflow"], "exit vim")
+            .await
+            .unwrap();
+        search.config.tags = vec![String::from("vim")];
+        assert_eq!(search.search().await.unwrap().len(), 2);
+        search.config.tags = vec![String::from("emacs")];
+        let results = search.search().await;
+        assert!(matches!(results, Err(Error::NoResults)));
+    }
+
+    // The project tag only applies on StackOverflow
+    let sites = ["stackoverflow", "askubuntu"];
+    let mut search = fixture_search_for(&[SearchEngine::StackExchange], &sites, "exit vim")
+        .await
+        .unwrap();
+    search.config.project_tag = Some(String::from("emacs"));
+    let results = keys(search.search().await.unwrap());
+    assert_eq!(results, ids(&[("askubuntu", 24406)]));
+
+    // Search engines get it as a term only when searching just StackOverflow
+    let mut search = fixture_search_for(&[SearchEngine::Google], &sites, "exit vim")
+        .await
+        .unwrap();
+    search.config.project_tag = Some(String::from("emacs"));
+    assert_eq!(search.search().await.unwrap().len(), 3);
+    search.config.sites = vec![String::from("stackoverflow")];
+    let results = search.search().await;
+    assert!(matches!(results, Err(Error::NoResults)));
+}
+
+#[tokio::test]
+async fn test_filters() {
+    // Filtered the same by the API and client side
+    for engine in [SearchEngine::StackExchange, SearchEngine::Google] {
+        let sites = ["stackoverflow", "askubuntu"];
+        let mut search = fixture_search_for(&[engine], &sites, "exit vim")
+            .await
+            .unwrap();
+        search.config.filters = SearchFilters {
+            min_score: Some(1000),
+            sort: SearchSort::Creation,
+            ..SearchFilters::default()
+        };
+        assert_eq!(
+            keys(search.search().await.unwrap()),
+            ids(&[("stackoverflow", 11828270), ("askubuntu", 24406)])
+        );
+
+        for filters in [
+            SearchFilters {
+                accepted: true,
+                ..SearchFilters::default()
+            },
+            SearchFilters {
+                exclude_closed: true,
+                ..SearchFilters::default()
+            },
+        ] {
+            search.config.filters = filters;
+            let results = keys(search.search().await.unwrap());
+            assert_eq!(results.len(), 2);
+            assert!(!results.contains(&(String::from("stackoverflow"), 25919461)));
+        }
+
+        search.config.filters = SearchFilters {
+            from_date: Some("1970-03-01".parse().unwrap()),
+            to_date: Some("1970-06-01".parse().unwrap()),
+            ..SearchFilters::default()
+        };
+        assert_eq!(
+            keys(search.search().await.unwrap()),
+            ids(&[("stackoverflow", 11828270)])
+        );
+    }
+
+    // Scraped questions must have every word of the query in their title, save
+    // for stopwords
+    let sites = ["stackoverflow", "askubuntu"];
+    let query = "What is the question on Stackoverflow?";
+    let mut search = fixture_search_for(&[SearchEngine::Google], &sites, query)
+        .await
+        .unwrap();
+    search.config.filters = SearchFilters {
+        title_only: true,
+        sort: SearchSort::Votes,
+        ..SearchFilters::default()
+    };
+    assert_eq!(
+        keys(search.search().await.unwrap()),
+        ids(&[("stackoverflow", 11828270), ("stackoverflow", 25919461)])
+    );
+}
+
+#[tokio::test]
+async fn test_low_quota() {
+    let engines = [SearchEngine::StackExchange];
+    let search = fixture_search_for(&engines, &["stackoverflow"], "exit vim")
+        .await
+        .unwrap();
+    search.search().await.unwrap();
+    search.warn_low_quota();
+    assert!(search.take_warnings().is_empty());
+
+    let search = fixture_search_for(&engines, &["stackoverflow"], "low quota")
+        .await
+        .unwrap();
+    search.search().await.unwrap();
+    search.warn_low_quota();
+    let warnings = search.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].starts_with("Only 42 StackExchange API requests remain"));
+}
+
+#[tokio::test]
+async fn test_no_results() {
+    let results = search(&[SearchEngine::StackExchange], &["serverfault"]).await;
+    assert!(matches!(results, Err(Error::NoResults)));
+}
+
+#[tokio::test]
+async fn test_duckduckgo_blocked() {
+    let results = search(&[SearchEngine::DuckDuckGo], &["stackoverflow"]).await;
+    assert!(matches!(results, Err(Error::Scraping(_))));
+}
+
+#[tokio::test]
+async fn test_engine_fallback() {
+    let engines = [SearchEngine::DuckDuckGo, SearchEngine::Google];
+    let (results, engine) = search(&engines, &["stackoverflow"]).await.unwrap();
+    assert_eq!(engine, Some(SearchEngine::Google));
+    assert_eq!(
+        results,
+        ids(&[("stackoverflow", 11828270), ("stackoverflow", 25919461)])
+    );
+
+    // Not worth falling back when there are simply no results
+    let engines = [SearchEngine::StackExchange, SearchEngine::Google];
+    let results = search(&engines, &["serverfault"]).await;
+    assert!(matches!(results, Err(Error::NoResults)));
+}
+
+#[tokio::test]
+async fn test_google_blocked() {
+    let results = search(&[SearchEngine::Google], &["captcha"]).await;
+    assert!(matches!(results, Err(Error::Captcha(engine)) if engine == "Google"));
+
+    let results = search(&[SearchEngine::Google], &["ratelimited"]).await;
+    assert!(matches!(
+        results,
+        Err(Error::RateLimited { status: 503, .. })
+    ));
+
+    // Blocked searches fall back to the next engine
+    let engines = [SearchEngine::Google, SearchEngine::StackExchange];
+    let (results, engine) = search(&engines, &["stackoverflow", "ratelimited"])
+        .await
+        .unwrap();
+    assert_eq!(engine, Some(SearchEngine::StackExchange));
+    assert_eq!(results.len(), 2);
+}
+
+#[tokio::test]
+async fn test_bing_fallback() {
+    // Brave serves a captcha
+    let engines = [SearchEngine::Brave, SearchEngine::Bing];
+    let (results, engine) = search(&engines, &["stackoverflow", "askubuntu"])
+        .await
+        .unwrap();
+    assert_eq!(engine, Some(SearchEngine::Bing));
+    assert_eq!(
+        results,
+        ids(&[
+            ("stackoverflow", 11828270),
+            ("askubuntu", 24406),
+            ("stackoverflow", 25919461)
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_searxng_search() {
+    let (results, engine) = search(&[SearchEngine::Searxng], &["stackoverflow", "askubuntu"])
+        .await
+        .unwrap();
+    assert_eq!(engine, Some(SearchEngine::Searxng));
+    assert_eq!(
+        results,
+        ids(&[
+            ("stackoverflow", 11828270),
+            ("askubuntu", 24406),
+            ("stackoverflow", 25919461)
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_custom_engine() {
+    let engine = SearchEngine::from(String::from("custom"));
+    let (results, _) = search(&[engine], &["stackoverflow", "askubuntu"])
+        .await
+        .unwrap();
+    assert_eq!(
+        results,
+        ids(&[
+            ("stackoverflow", 25919461),
+            ("stackoverflow", 11828270),
+            ("askubuntu", 24406)
+        ])
+    );
+
+    let engine = SearchEngine::from(String::from("undefined"));
+    let results = search(&[engine], &["stackoverflow"]).await;
+    assert!(matches!(results, Err(Error::UnknownEngine(name)) if name == "undefined"));
+}
+
+#[tokio::test]
+async fn test_answer_links() {
+    let sites = ["stackoverflow", "askubuntu"];
+    let search = fixture_search_for(&[SearchEngine::Google], &sites, "answer links")
+        .await
+        .unwrap();
+    let qs = search.search().await.unwrap();
+    let results = qs
+        .iter()
+        .map(|q| (q.site.clone().unwrap_or_default(), q.id, q.linked_answer))
+        .collect::<Vec<_>>();
+    // The share link without a question id is resolved to its question
+    assert_eq!(
+        results,
+        vec![
+            (String::from("stackoverflow"), 11828270, Some(11828272)),
+            (String::from("askubuntu"), 24406, Some(24407)),
+            (String::from("stackoverflow"), 25919461, None),
+        ]
+    );
+
+    // Lucky mode prefers the linked answer over the top one
+    let mut search = fixture_search_for(&[SearchEngine::Google], &sites, "answer links")
+        .await
+        .unwrap();
+    let lucky = search.search_lucky().await.unwrap();
+    assert_eq!(lucky.answer.id, 11828272);
+}
+
+#[tokio::test]
+async fn test_colliding_ids() {
+    let sites = ["stackoverflow", "superuser"];
+    let (results, _) = search(&[SearchEngine::Google], &sites).await.unwrap();
+    assert_eq!(
+        results,
+        ids(&[
+            ("superuser", 11828270),
+            ("stackoverflow", 11828270),
+            ("stackoverflow", 25919461)
+        ])
+    );
+    let (results, _) = search(&[SearchEngine::StackExchange], &sites)
+        .await
+        .unwrap();
+    assert_eq!(
+        results,
+        ids(&[
+            ("stackoverflow", 11828270),
+            ("stackoverflow", 25919461),
+            ("superuser", 11828270)
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_comments() {
+    let (qs, search) = search_questions(&[SearchEngine::StackExchange], &["stackoverflow"])
+        .await
+        .unwrap();
+    // Comments are left for the TUI to fetch once expanded
+    assert!(qs.iter().all(|q| q.comments.is_empty()));
+    let comments = search
+        .comments_md("stackoverflow", 11828270, &[11828271, 11828272])
+        .await
+        .unwrap();
+    let mut posts = comments.keys().copied().collect::<Vec<_>>();
+    posts.sort_unstable();
+    assert_eq!(posts, [11828270, 11828271, 11828272]);
+    assert!(comments.values().all(|cs| cs.len() == 1));
+}
+
+#[tokio::test]
+async fn test_meta_search() {
+    let (qs, search) = search_questions(&[SearchEngine::Meta], &["stackoverflow", "askubuntu"])
+        .await
+        .unwrap();
+    assert_eq!(search.engine(), Some(SearchEngine::Meta));
+    // DuckDuckGo is blocked; Google and StackExchange rankings are fused
+    let results = qs
+        .iter()
+        .map(|q| (q.site.clone().unwrap_or_default(), q.id))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        ids(&[
+            ("stackoverflow", 11828270),
+            ("askubuntu", 24406),
+            ("stackoverflow", 25919461)
+        ])
+    );
+    assert!(qs
+        .iter()
+        .all(|q| q.engines == [SearchEngine::Google, SearchEngine::StackExchange]));
+}
+
+#[tokio::test]
+async fn test_partial_failure() {
+    let (qs, partial) =
+        search_questions(&[SearchEngine::StackExchange], &["stackoverflow", "broken"])
+            .await
+            .unwrap();
+    assert_eq!(qs.len(), 2);
+    let warnings = partial.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].starts_with("Skipped broken"));
+    assert!(partial.take_warnings().is_empty());
+
+    // Nothing to salvage
+    let results = search(&[SearchEngine::StackExchange], &["broken"]).await;
+    assert!(matches!(
+        results,
+        Err(Error::StackExchangeApi { id: 400, .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_search_streaming() {
+    let sites = ["stackoverflow", "askubuntu"];
+    let search = fixture_search_for(&[SearchEngine::Google], &sites, "exit vim")
+        .await
+        .unwrap();
+    let (tx, mut batches) = mpsc::unbounded_channel();
+    let qs = search.search_streaming(tx).await.unwrap();
+    let mut streamed = Vec::new();
+    while let Some((site, batch)) = batches.recv().await {
+        assert!(batch.iter().all(|q| q.site.as_ref() == Some(&site)));
+        streamed.extend(batch.into_iter().map(|q| (site.clone(), q.id)));
+    }
+    streamed.sort();
+    assert_eq!(
+        streamed,
+        ids(&[
+            ("askubuntu", 24406),
+            ("stackoverflow", 11828270),
+            ("stackoverflow", 25919461)
+        ])
+    );
+    // The final result is still ranked
+    assert_eq!(qs[0].id, 11828270);
+}